@@ -0,0 +1,266 @@
+// This file provides a parser and writer for Advanced SubStation Alpha (.ass)
+// and SubStation Alpha (.ssa) subtitle files.  Only the events are read:
+// script information and style definitions are skipped
+use super::cue::{Cue, Problem};
+
+// The event fields written by older tools which leave out the "Format" line
+const DEFAULT_FORMAT: [&str; 10] = [
+    "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+// Parse an ASS or SSA file into its cues, along with any problems found in
+// it.  Text is split into lines at hard ("\N") and soft ("\n") line breaks.
+// Events may overlap, or be out of order, as they can be drawn at once
+pub fn parse(contents: &str) -> (Vec<Cue>, Vec<Problem>) {
+    let mut cues = Vec::new();
+    let mut problems = Vec::new();
+    let mut section = String::new();
+    let mut seen_events = false;
+    let mut format: Vec<String> = Vec::new();
+    for (i, line) in contents.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line_num = i + 1;
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_lowercase();
+            if section == "events" {
+                seen_events = true;
+            }
+            continue;
+        }
+        if section != "events" {
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim_start()),
+            None => continue,
+        };
+        if key.eq_ignore_ascii_case("format") {
+            format = value.split(',').map(|f| f.trim().to_string()).collect();
+            continue;
+        }
+        if !key.eq_ignore_ascii_case("dialogue") {
+            continue;
+        }
+        if format.is_empty() {
+            problems.push(Problem::new(
+                line_num,
+                "Dialogue before the \"Format\" line of the [Events] section",
+            ));
+            format = DEFAULT_FORMAT.iter().map(|f| f.to_string()).collect();
+        }
+        // The text is the last field, and may itself contain commas
+        let fields: Vec<&str> = value.splitn(format.len(), ',').collect();
+        if fields.len() != format.len() {
+            problems.push(Problem::new(
+                line_num,
+                format!(
+                    "Expected {} fields in dialogue, found {}",
+                    format.len(),
+                    fields.len()
+                ),
+            ));
+            continue;
+        }
+        let field = |name: &str| {
+            format
+                .iter()
+                .position(|f| f.eq_ignore_ascii_case(name))
+                .map(|i| fields[i])
+        };
+        let (start, end) = match (field("Start"), field("End")) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                problems.push(Problem::new(line_num, "Dialogue has no start or end time"));
+                continue;
+            }
+        };
+        let (start_ms, end_ms) = match (parse_timestamp(start), parse_timestamp(end)) {
+            (Some(start_ms), Some(end_ms)) => (start_ms, end_ms),
+            _ => {
+                problems.push(Problem::new(
+                    line_num,
+                    format!("Malformed timestamps \"{}\" and \"{}\"", start, end),
+                ));
+                continue;
+            }
+        };
+        if end_ms < start_ms {
+            problems.push(Problem::new(line_num, "Dialogue ends before it starts"));
+        }
+        let text = field("Text").unwrap_or("");
+        if text.trim().is_empty() {
+            problems.push(Problem::new(line_num, "Dialogue has no text"));
+        }
+        let style = field("Style").unwrap_or("").trim_start_matches('*');
+        cues.push(Cue {
+            start: start_ms,
+            end: end_ms,
+            line: line_num,
+            text: text
+                .replace("\\n", "\\N")
+                .split("\\N")
+                .map(|l| l.to_string())
+                .collect(),
+            settings: String::new(),
+            style: if style.eq_ignore_ascii_case("default") {
+                String::new()
+            } else {
+                style.to_string()
+            },
+        });
+    }
+    if !seen_events {
+        problems.push(Problem::new(1, "Missing [Events] section"));
+    }
+    (cues, problems)
+}
+
+// Parse "H:MM:SS.cc" into milliseconds
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let (hms, cs) = s.trim().split_once('.')?;
+    if cs.len() != 2 || !cs.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let parts: Vec<i64> = hms
+        .split(':')
+        .map(|p| {
+            if p.is_empty() || !p.chars().all(|c| c.is_ascii_digit()) {
+                None
+            } else {
+                p.parse().ok()
+            }
+        })
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        // Hours with too many digits would overflow
+        [h, m, s] if *m < 60 && *s < 60 => h
+            .checked_mul(3_600_000)?
+            .checked_add((m * 60 + s) * 1000 + cs.parse::<i64>().ok()? * 10),
+        _ => None,
+    }
+}
+
+// Format milliseconds as "H:MM:SS.cc", rounding to the nearest centisecond
+pub fn format_timestamp(ms: i64) -> String {
+    let cs = (ms.max(0) + 5) / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        (cs / 6000) % 60,
+        (cs / 100) % 60,
+        cs % 100
+    )
+}
+
+// Write cues as an ASS file (or an SSA file, with `ssa`) using a single
+// default style.  Cue text should already use ASS markup
+pub fn write(cues: &[Cue], ssa: bool) -> String {
+    let mut out = String::new();
+    if ssa {
+        out.push_str("[Script Info]\nScriptType: v4.00\n\n");
+        out.push_str("[V4 Styles]\n");
+        out.push_str("Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, TertiaryColour, BackColour, Bold, Italic, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, AlphaLevel, Encoding\n");
+        out.push_str("Style: Default,Arial,20,16777215,65535,65535,0,0,0,1,2,2,2,10,10,10,0,1\n\n");
+        out.push_str("[Events]\n");
+        out.push_str(
+            "Format: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        );
+    } else {
+        out.push_str("[Script Info]\nScriptType: v4.00+\nPlayResX: 384\nPlayResY: 288\n\n");
+        out.push_str("[V4+ Styles]\n");
+        out.push_str("Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n");
+        out.push_str("Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1\n\n");
+        out.push_str("[Events]\n");
+        out.push_str(
+            "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        );
+    }
+    for cue in cues {
+        out.push_str(&format!(
+            "Dialogue: {},{},{},Default,,0,0,0,,{}\n",
+            if ssa { "Marked=0" } else { "0" },
+            format_timestamp(cue.start),
+            format_timestamp(cue.end),
+            cue.text.join("\\N")
+        ));
+    }
+    out
+}
+
+// Rewrite the start and end times of every event with `f`
+pub fn retime(contents: &str, f: &dyn Fn(i64) -> i64) -> String {
+    map_events(contents, |_, format, fields| {
+        for (i, field) in format.iter().enumerate() {
+            if !field.eq_ignore_ascii_case("start") && !field.eq_ignore_ascii_case("end") {
+                continue;
+            }
+            if let Some(ms) = fields.get(i).and_then(|v| parse_timestamp(v)) {
+                // Keep any space after the colon which starts the fields
+                let leading = &fields[i][..fields[i].len() - fields[i].trim_start().len()];
+                fields[i] = format!("{}{}", leading, format_timestamp(f(ms)));
+            }
+        }
+        true
+    })
+}
+
+// Rewrite the text of every dialogue event with `f`, which returns None to
+// remove the event.  Lines are separated by "\N" in the text
+pub fn map_text(contents: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    map_events(contents, |kind, format, fields| {
+        if !kind.eq_ignore_ascii_case("dialogue") {
+            return true;
+        }
+        let i = match format.iter().position(|f| f.eq_ignore_ascii_case("text")) {
+            Some(i) if i < fields.len() => i,
+            _ => return true,
+        };
+        match f(&fields[i]) {
+            Some(text) => {
+                fields[i] = text;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+// Rewrite the events of a file, leaving everything else as it is.  `f` is
+// given each event's kind (e.g., "Dialogue"), the names of its fields, and
+// the fields to change, and returns whether to keep the event
+fn map_events(
+    contents: &str,
+    mut f: impl FnMut(&str, &[String], &mut Vec<String>) -> bool,
+) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut in_events = false;
+    let mut format: Vec<String> = DEFAULT_FORMAT.iter().map(|f| f.to_string()).collect();
+    for line in contents.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[text.len()..];
+        let trimmed = text.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_events = trimmed.eq_ignore_ascii_case("[events]");
+        }
+        let (key, value) = match text.split_once(':') {
+            Some((key, value)) if in_events => (key, value),
+            _ => {
+                out.push_str(line);
+                continue;
+            }
+        };
+        let kind = key.trim().trim_start_matches('\u{feff}');
+        if kind.eq_ignore_ascii_case("format") {
+            format = value.split(',').map(|f| f.trim().to_string()).collect();
+        }
+        if !kind.eq_ignore_ascii_case("dialogue") && !kind.eq_ignore_ascii_case("comment") {
+            out.push_str(line);
+            continue;
+        }
+        let mut fields: Vec<String> = value.splitn(format.len(), ',').map(String::from).collect();
+        if f(kind, &format, &mut fields) {
+            out.push_str(&format!("{}:{}{}", key, fields.join(","), ending));
+        }
+    }
+    out
+}
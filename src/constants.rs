@@ -17,12 +17,24 @@ const _AUDIOBOOK_TYPES: [&str; 20] = [
 
 pub const SUBTITLE_TYPES: [&str; 5] = ["srt", "smi", "ssa", "ass", "vtt"];
 
+// Image-based subtitles: VobSub (an .idx index with its .sub images) and PGS
+// (.sup), as ripped from DVDs and Blu-rays
+pub const BITMAP_SUBTITLE_TYPES: [&str; 3] = ["idx", "sub", "sup"];
+
+// Flags Plex understands for forced subtitles, and subtitles for the deaf and
+// hard of hearing
+pub const SUBTITLE_FLAGS: [&str; 3] = ["forced", "sdh", "cc"];
+
 lazy_static! {
     pub static ref FILM_RE: Regex = Regex::new(r"^(?P<fname>.+)\s+\((?P<fyear>\d{4})\)$").unwrap();
     pub static ref SEASON_RE: Regex = Regex::new(r"^Season\s(?P<snum>\d{2,})(\s\-\s(?P<sname>.+))?$").unwrap();
     // pub static ref EP_RE: Regex = Regex::new(r"^(.*)\s\-\sS(\d+)E(\d+)(?:\s\-\s)(?:.*)\.(.*)$").unwrap();  // THIS WAS BUGGED - DOES NOT WORK!
     pub static ref EP_RE: Regex = Regex::new(r"^(?P<sname>.+)\s\-\sS(?P<snum>\d+)E(?P<epnum>\d{2,})(\s-\s)?(?P<epname>.+)?\.(?P<ext>\w+)$").unwrap();
-    static ref SUB_EXT_RE: Regex = Regex::new(&SUBTITLE_TYPES.join("|")).unwrap();
-    static ref SUB_RE_STR: String = format!(r"^(?P<fname>.+)\.(?P<locale>(\w{{2}}(\-\w{{2}})?)|\w{{3}})\.({})$", SUB_EXT_RE.to_string());
+    // Absolute episode numbering (e.g., for anime): "Show - 137.mkv" or "Show - E137 - Title.mkv"
+    pub static ref ABS_EP_RE: Regex = Regex::new(r"^(?P<sname>.+)\s\-\sE?(?P<absnum>\d{2,})(\s-\s(?P<epname>.+))?\.(?P<ext>\w+)$").unwrap();
+    static ref SUB_EXT_RE: Regex = Regex::new(&[SUBTITLE_TYPES.join("|"), BITMAP_SUBTITLE_TYPES.join("|")].join("|")).unwrap();
+    // Subtitles may have flags after the locale (e.g., "Film (2001).en.forced.srt")
+    static ref SUB_FLAGS_RE_STR: String = format!(r"(?:{})", SUBTITLE_FLAGS.join("|"));
+    static ref SUB_RE_STR: String = format!(r"^(?P<fname>.+?)\.(?P<locale>(\w{{2}}(\-\w{{2}})?)|\w{{3}})(?P<flags>(\.{flag})*)\.({ext})$", flag = *SUB_FLAGS_RE_STR, ext = SUB_EXT_RE.to_string());
     pub static ref SUB_RE: Regex = Regex::new(&SUB_RE_STR).unwrap();
 }
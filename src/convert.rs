@@ -0,0 +1,434 @@
+// This file provides conversion of subtitle files between formats.
+//
+// Cue text is translated through SRT markup, which every format can express
+// at least in part: italic, bold, and underlined text, and font colours.
+// Anything else (positioning, named styles, effects) is dropped, and reported
+// as lost so that the original can be kept where it matters
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use colored::*;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::ass;
+use super::constants;
+use super::cue::{Cue, SubtitleFormat};
+use super::encoding;
+use super::smi;
+use super::srt;
+use super::subtitles::{self, SubtitleName};
+use super::vtt;
+
+lazy_static! {
+    // An HTML-like tag, as used by SRT, WebVTT, and SAMI
+    static ref TAG_RE: Regex = Regex::new(r"<(?P<close>/?)(?P<name>[^\s./>]*)(?P<rest>[^>]*)>").unwrap();
+    // An ASS override block
+    static ref OVERRIDE_RE: Regex = Regex::new(r"\{(?P<tags>[^}]*)\}").unwrap();
+    static ref ASS_TOGGLE_RE: Regex = Regex::new(r"^(?P<tag>[ibu])(?P<value>\d*)$").unwrap();
+    static ref ASS_COLOUR_RE: Regex = Regex::new(r"^1?c(&H(?P<colour>[0-9a-fA-F]{1,8})&?)?$").unwrap();
+    static ref FONT_COLOUR_RE: Regex = Regex::new(r#"(?i)color\s*=\s*["']?#(?P<colour>[0-9a-fA-F]{6})\b"#).unwrap();
+    static ref ENTITY_RE: Regex = Regex::new(r"&(amp|lt|gt|quot|apos|nbsp|lrm|rlm);").unwrap();
+}
+
+// Styling lost in conversion, described for the user
+type Lost = BTreeSet<&'static str>;
+
+// Convert subtitle files to another format, writing each alongside the
+// original with the same name (and so the same language and flags)
+pub fn convert_subtitles(paths: &[PathBuf], to: &str) {
+    let to = match SubtitleFormat::from_extension(to) {
+        Some(to) => to,
+        None => {
+            eprintln!(
+                "[ERROR] Cannot convert to \"{}\"; expected one of {}",
+                to,
+                constants::SUBTITLE_TYPES.join(", ")
+            );
+            process::exit(1);
+        }
+    };
+    let mut found = 0;
+    let mut converted = 0;
+    for sub in subtitles::find_subtitles(paths) {
+        let from = match SubtitleFormat::from_path(&sub) {
+            Some(from) if from != to => from,
+            _ => continue,
+        };
+        found += 1;
+        let dest = sub.with_extension(to.extension());
+        if dest.exists() {
+            eprintln!(
+                "[ERROR] Cannot convert {:?}: {:?} already exists",
+                sub, dest
+            );
+            continue;
+        }
+        let contents = match encoding::read_to_string(&sub) {
+            Ok((contents, _)) => contents,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", sub, e);
+                continue;
+            }
+        };
+        let (cues, problems) = from.parse(&contents);
+        if cues.is_empty() {
+            eprintln!("[ERROR] Cannot convert {:?}: no cues found", sub);
+            continue;
+        }
+        let (output, lost) = convert(&sub, &contents, from, to, cues);
+        if let Err(e) = fs::write(&dest, output) {
+            eprintln!("[ERROR] Cannot write {:?}: {}", dest, e);
+            continue;
+        }
+        converted += 1;
+        println!(
+            "{}{}{}",
+            sub.display().to_string().bold(),
+            " converted to ".italic(),
+            dest.file_name().unwrap().to_string_lossy()
+        );
+        if !problems.is_empty() {
+            println!(
+                "\t{}",
+                format!(
+                    "{} problems found while reading; see `filmls subtitles validate`",
+                    problems.len()
+                )
+                .yellow()
+            );
+        }
+        if !lost.is_empty() {
+            println!(
+                "\t{}{}",
+                "Styling lost: ".italic(),
+                lost.into_iter().collect::<Vec<_>>().join(", ").yellow()
+            );
+        }
+    }
+    println!(
+        "{}{}{}{}",
+        "Converted ".italic(),
+        converted.to_string().bold(),
+        " of ".italic(),
+        format!("{} subtitle files to {}", found, to).italic()
+    );
+}
+
+fn convert(
+    sub: &Path,
+    contents: &str,
+    from: SubtitleFormat,
+    to: SubtitleFormat,
+    mut cues: Vec<Cue>,
+) -> (String, Lost) {
+    let mut lost = Lost::new();
+    match from {
+        SubtitleFormat::Ass | SubtitleFormat::Ssa => {
+            lost.insert("style definitions");
+            if cues.iter().any(|c| !c.style.is_empty()) {
+                lost.insert("named styles");
+            }
+            // Events may be out of order, and lines shown together are
+            // often separate events
+            cues.sort_by_key(|c| c.start);
+            cues.dedup_by(|next, prev| {
+                let same = (next.start, next.end) == (prev.start, prev.end);
+                if same {
+                    prev.text.append(&mut next.text);
+                }
+                same
+            });
+        }
+        SubtitleFormat::Vtt if contents.lines().any(|l| l.trim_end() == "STYLE") => {
+            lost.insert("STYLE blocks");
+        }
+        _ => (),
+    }
+    for cue in cues.iter_mut() {
+        let text = to_srt_markup(from, cue, &mut lost);
+        cue.text = from_srt_markup(to, &text, &mut lost);
+    }
+    let output = match to {
+        SubtitleFormat::Srt => srt::write(&cues),
+        SubtitleFormat::Vtt => vtt::write(&cues),
+        SubtitleFormat::Ass => ass::write(&cues, false),
+        SubtitleFormat::Ssa => ass::write(&cues, true),
+        SubtitleFormat::Smi => {
+            let name = sub.file_name().unwrap().to_string_lossy();
+            let lang = SubtitleName::parse(&name)
+                .map(|n| n.locale)
+                .unwrap_or_else(|| "und".to_string());
+            smi::write(&cues, &lang)
+        }
+    };
+    (output, lost)
+}
+
+// Translate a cue's text into SRT markup
+fn to_srt_markup(from: SubtitleFormat, cue: &Cue, lost: &mut Lost) -> Vec<String> {
+    match from {
+        SubtitleFormat::Srt => cue.text.clone(),
+        SubtitleFormat::Vtt => {
+            if !cue.settings.is_empty() {
+                lost.insert("cue positioning");
+            }
+            cue.text.iter().map(|l| vtt_to_srt(l, lost)).collect()
+        }
+        SubtitleFormat::Smi => cue.text.iter().map(|l| smi_to_srt(l, lost)).collect(),
+        SubtitleFormat::Ass | SubtitleFormat::Ssa => ass_to_srt(&cue.text, lost),
+    }
+}
+
+// Translate SRT markup into the markup of another format
+fn from_srt_markup(to: SubtitleFormat, text: &[String], lost: &mut Lost) -> Vec<String> {
+    if matches!(to, SubtitleFormat::Ass | SubtitleFormat::Ssa) {
+        return srt_to_ass(text, lost);
+    }
+    // SRT files often carry ASS positioning tags, which only ASS understands
+    if text.iter().any(|l| OVERRIDE_RE.is_match(l)) {
+        lost.insert("positioning");
+    }
+    let text = text
+        .iter()
+        .map(|l| OVERRIDE_RE.replace_all(l, "").to_string());
+    match to {
+        SubtitleFormat::Vtt => text.map(|l| srt_to_html(&l, false, lost)).collect(),
+        SubtitleFormat::Smi => text.map(|l| srt_to_html(&l, true, lost)).collect(),
+        _ => text.collect(),
+    }
+}
+
+fn vtt_to_srt(line: &str, lost: &mut Lost) -> String {
+    let line = TAG_RE.replace_all(line, |caps: &regex::Captures| {
+        let name = caps["name"].to_lowercase();
+        match name.as_str() {
+            "i" | "b" | "u" => {
+                if caps["rest"].starts_with('.') {
+                    lost.insert("class styling");
+                }
+                format!("<{}{}>", &caps["close"], name)
+            }
+            "c" => {
+                lost.insert("class styling");
+                String::new()
+            }
+            "v" => {
+                lost.insert("speaker names");
+                String::new()
+            }
+            "ruby" | "rt" => {
+                lost.insert("ruby text");
+                String::new()
+            }
+            "lang" => String::new(),
+            _ if name.starts_with(|c: char| c.is_ascii_digit()) => {
+                lost.insert("karaoke timings");
+                String::new()
+            }
+            _ => caps[0].to_string(),
+        }
+    });
+    decode_entities(&line)
+}
+
+fn smi_to_srt(line: &str, lost: &mut Lost) -> String {
+    let line = TAG_RE.replace_all(line, |caps: &regex::Captures| {
+        let name = caps["name"].to_lowercase();
+        match name.as_str() {
+            "i" | "b" | "u" => format!("<{}{}>", &caps["close"], name),
+            "font" => format!("<{}font{}>", &caps["close"], &caps["rest"]),
+            _ => {
+                lost.insert("SAMI markup");
+                String::new()
+            }
+        }
+    });
+    decode_entities(&line)
+}
+
+fn decode_entities(line: &str) -> String {
+    ENTITY_RE
+        .replace_all(line, |caps: &regex::Captures| match &caps[1] {
+            "amp" => "&",
+            "lt" => "<",
+            "gt" => ">",
+            "quot" => "\"",
+            "apos" => "'",
+            "nbsp" => "\u{a0}",
+            _ => "",
+        })
+        .to_string()
+}
+
+// Translate SRT markup into WebVTT markup (or, with `font`, SAMI markup),
+// escaping text which would otherwise be read as markup
+fn srt_to_html(line: &str, font: bool, lost: &mut Lost) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for caps in TAG_RE.captures_iter(line) {
+        let tag = caps.get(0).unwrap();
+        out.push_str(&escape_html(&line[last..tag.start()]));
+        last = tag.end();
+        let name = caps["name"].to_lowercase();
+        match name.as_str() {
+            "i" | "b" | "u" => out.push_str(&format!("<{}{}>", &caps["close"], name)),
+            "font" if font => out.push_str(tag.as_str()),
+            "font" => {
+                lost.insert("font colours and faces");
+            }
+            _ => out.push_str(&escape_html(tag.as_str())),
+        }
+    }
+    out.push_str(&escape_html(&line[last..]));
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\u{a0}', "&nbsp;")
+}
+
+// Text styling which can be expressed in every format
+#[derive(Default, Clone, PartialEq)]
+struct Style {
+    italic: bool,
+    bold: bool,
+    underline: bool,
+    // As "#RRGGBB"
+    colour: Option<String>,
+}
+
+impl Style {
+    fn open_tags(&self) -> String {
+        let mut tags = String::new();
+        if let Some(colour) = &self.colour {
+            tags.push_str(&format!("<font color=\"{}\">", colour));
+        }
+        for (set, tag) in [(self.bold, "b"), (self.italic, "i"), (self.underline, "u")] {
+            if set {
+                tags.push_str(&format!("<{}>", tag));
+            }
+        }
+        tags
+    }
+
+    fn close_tags(&self) -> String {
+        let mut tags = String::new();
+        for (set, tag) in [(self.underline, "u"), (self.italic, "i"), (self.bold, "b")] {
+            if set {
+                tags.push_str(&format!("</{}>", tag));
+            }
+        }
+        if self.colour.is_some() {
+            tags.push_str("</font>");
+        }
+        tags
+    }
+}
+
+// Translate a cue's ASS text into SRT markup.  Styling carries across line
+// breaks in ASS, so tags are closed at the end of each line and reopened
+fn ass_to_srt(text: &[String], lost: &mut Lost) -> Vec<String> {
+    let mut style = Style::default();
+    let mut lines = Vec::new();
+    for line in text {
+        let mut out = style.open_tags();
+        let mut last = 0;
+        for caps in OVERRIDE_RE.captures_iter(line) {
+            let block = caps.get(0).unwrap();
+            out.push_str(&line[last..block.start()].replace("\\h", "\u{a0}"));
+            last = block.end();
+            let mut new_style = style.clone();
+            for tag in caps["tags"]
+                .split('\\')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+            {
+                apply_ass_tag(tag, &mut new_style, lost);
+            }
+            if new_style != style {
+                out.push_str(&style.close_tags());
+                out.push_str(&new_style.open_tags());
+                style = new_style;
+            }
+        }
+        out.push_str(&line[last..].replace("\\h", "\u{a0}"));
+        out.push_str(&style.close_tags());
+        lines.push(out);
+    }
+    lines
+}
+
+fn apply_ass_tag(tag: &str, style: &mut Style, lost: &mut Lost) {
+    if let Some(caps) = ASS_TOGGLE_RE.captures(tag) {
+        // "\b" also takes a font weight, where anything but 0 is bold
+        let on = !matches!(&caps["value"], "" | "0");
+        match &caps["tag"] {
+            "i" => style.italic = on,
+            "b" => style.bold = on,
+            _ => style.underline = on,
+        }
+    } else if let Some(caps) = ASS_COLOUR_RE.captures(tag) {
+        // Colours are written as &HBBGGRR&, possibly with a leading alpha
+        style.colour = caps.name("colour").map(|c| {
+            let c = format!("{:0>6}", c.as_str());
+            let c = &c[c.len() - 6..];
+            format!("#{}{}{}", &c[4..6], &c[2..4], &c[0..2]).to_uppercase()
+        });
+    } else if tag == "r" {
+        *style = Style::default();
+    } else if ["pos", "an", "a", "move", "org"].iter().any(|t| {
+        tag.starts_with(t) && tag[t.len()..].starts_with(|c: char| c.is_ascii_digit() || c == '(')
+    }) {
+        lost.insert("positioning");
+    } else {
+        lost.insert("effects (fonts, fades, karaoke)");
+    }
+}
+
+// Translate SRT markup into ASS override tags
+fn srt_to_ass(text: &[String], lost: &mut Lost) -> Vec<String> {
+    text.iter()
+        .map(|line| {
+            // Whether each <font> tag set a colour, so that </font> can
+            // reset it
+            let mut fonts = Vec::new();
+            TAG_RE
+                .replace_all(line, |caps: &regex::Captures| {
+                    let name = caps["name"].to_lowercase();
+                    let on = if caps["close"].is_empty() { 1 } else { 0 };
+                    match name.as_str() {
+                        "i" | "b" | "u" => format!("{{\\{}{}}}", name, on),
+                        "font" if on == 1 => {
+                            let colour = FONT_COLOUR_RE.captures(&caps["rest"]);
+                            if colour.is_none() || caps["rest"].to_lowercase().contains("face") {
+                                lost.insert("font faces and named colours");
+                            }
+                            fonts.push(colour.is_some());
+                            match colour {
+                                Some(c) => {
+                                    let c = c["colour"].to_uppercase();
+                                    format!("{{\\c&H{}{}{}&}}", &c[4..6], &c[2..4], &c[0..2])
+                                }
+                                None => String::new(),
+                            }
+                        }
+                        "font" => {
+                            if fonts.pop().unwrap_or(false) {
+                                "{\\c}".to_string()
+                            } else {
+                                String::new()
+                            }
+                        }
+                        _ => caps[0].to_string(),
+                    }
+                })
+                .to_string()
+        })
+        .collect()
+}
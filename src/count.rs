@@ -1,14 +1,25 @@
-use std::convert::TryInto;
+use std::fs;
 use std::path::Path;
-use std::{fs, io};
 
 use colored::*;
 
 use super::constants;
-use super::dir::MediaType;
+use super::dir::{self, MediaType};
 use super::path;
 
+// Count the films or series in each library, with both libraries being
+// counted when the media type is not known.  In that case, a library which
+// does not exist is skipped
 pub fn show_count(dirname: &Path, media_type: &MediaType) {
+    for library in media_type.libraries() {
+        if *media_type == MediaType::Unknown && !dirname.join(library.as_str()).is_dir() {
+            continue;
+        }
+        count_library(dirname, &library);
+    }
+}
+
+fn count_library(dirname: &Path, media_type: &MediaType) {
     match media_type {
         MediaType::Film => {
             let mut films_dir = dirname.to_path_buf();
@@ -55,32 +66,14 @@ pub fn show_count(dirname: &Path, media_type: &MediaType) {
                 " television series in your Plex Media Server.".italic()
             );
         }
-        MediaType::Unknown | MediaType::Impossible => {
-            panic!("Unhandled media type");
-        }
+        MediaType::Unknown => unreachable!(),
     }
 }
 
 fn count_media_files(dir: &Path) -> usize {
-    // TODO: use walkdir or something (see jakewilliami/lsext)
-    fn recurse_files_count_if_media(dir: &Path, cnt: &mut isize) -> io::Result<()> {
-        if dir.is_dir() {
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    recurse_files_count_if_media(&path, cnt)?;
-                } else {
-                    let ext = path::get_extension_from_filename(&path);
-                    if ext.is_some() && constants::MEDIA_TYPES.contains(&ext.unwrap()) {
-                        *cnt += 1;
-                    }
-                }
-            }
-        }
-        Ok(())
-    }
-    let mut cnt = 0;
-    let _ = recurse_files_count_if_media(dir, &mut cnt);
-    cnt.try_into().unwrap()
+    dir::list_files(dir)
+        .unwrap_or_default()
+        .iter()
+        .filter(|p| path::is_media_file(p))
+        .count()
 }
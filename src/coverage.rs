@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use colored::*;
+
+use super::dir::{self, MediaType};
+use super::junk;
+use super::locale;
+use super::path;
+use super::subtitles::SubtitleName;
+
+// How many media files have subtitles
+#[derive(Default, Clone, Copy)]
+struct Coverage {
+    total: usize,
+    // Media files with subtitles in the chosen language
+    with_language: usize,
+    // Media files with subtitles in any language
+    with_any: usize,
+    // Media files whose only subtitles in the chosen language are image-based
+    image_only: usize,
+}
+
+impl Coverage {
+    fn add(&mut self, other: Coverage) {
+        self.total += other.total;
+        self.with_language += other.with_language;
+        self.with_any += other.with_any;
+        self.image_only += other.image_only;
+    }
+
+    fn describe(&self, language_name: &str) -> String {
+        let image_only = if self.image_only > 0 {
+            format!(" ({} image-based only)", self.image_only)
+        } else {
+            String::new()
+        };
+        format!(
+            "{}/{} ({}) have {} subtitles{}, {}/{} ({}) have any subtitles",
+            self.with_language,
+            self.total,
+            percentage(self.with_language, self.total),
+            language_name,
+            image_only,
+            self.with_any,
+            self.total,
+            percentage(self.with_any, self.total)
+        )
+    }
+}
+
+fn percentage(n: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}%", 100.0 * n as f64 / total as f64)
+    }
+}
+
+// A media file which is missing text subtitles in the chosen language
+struct Gap {
+    name: String,
+    // Whether the media file has no subtitles, other than forced ones
+    no_subtitles: bool,
+    // Whether the media file has image-based subtitles in the chosen language,
+    // which cannot be converted or searched like text
+    image_based: bool,
+    // Whether the media file's only subtitles in the chosen language are
+    // forced, which translate only foreign dialogue and signs
+    forced_only: bool,
+}
+
+// A subtitle alongside a media file
+struct Subtitle {
+    // The preferred code for the subtitle's language
+    language: String,
+    image_based: bool,
+    forced: bool,
+}
+
+// Report which films and episodes have subtitles in a given language, listing
+// those which do not
+pub fn show_subtitle_coverage(dirname: &Path, media_type: &MediaType, language: &str) {
+    let language = match locale::find_language(language) {
+        Some(language) => language,
+        None => {
+            eprintln!("[ERROR] Unknown language \"{}\"", language);
+            process::exit(1);
+        }
+    };
+    let code = locale::preferred_code(language);
+    for library in media_type.libraries() {
+        let mut library_dir = dirname.to_path_buf();
+        library_dir.push(library.as_str());
+        match library {
+            MediaType::Film => {
+                let (coverage, gaps) = directory_coverage(&library_dir, code);
+                println!(
+                    "{}: {}",
+                    library.as_str().blue().bold(),
+                    coverage.describe(language.name)
+                );
+                print_gaps(&gaps, 1);
+            }
+            MediaType::Series => {
+                let mut library_coverage = Coverage::default();
+                let mut lines = Vec::new();
+                let mut series: Vec<_> = fs::read_dir(&library_dir)
+                    .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", library_dir))
+                    .map(|e| e.expect("Cannot retreive file information").path())
+                    .filter(|p| p.is_dir())
+                    .collect();
+                series.sort();
+                for series_path in series {
+                    let series_name = series_path.file_name().unwrap().to_string_lossy();
+                    let seasons = season_coverage(&series_path, code);
+                    let mut series_coverage = Coverage::default();
+                    for (coverage, _) in seasons.values() {
+                        series_coverage.add(*coverage);
+                    }
+                    if series_coverage.total == 0 {
+                        continue;
+                    }
+                    library_coverage.add(series_coverage);
+                    lines.push((series_name.to_string(), series_coverage, seasons));
+                }
+                println!(
+                    "{}: {}",
+                    library.as_str().blue().bold(),
+                    library_coverage.describe(language.name)
+                );
+                for (series_name, coverage, seasons) in lines {
+                    println!(
+                        "\t{}: {}",
+                        series_name.blue().bold(),
+                        coverage.describe(language.name)
+                    );
+                    for (season, (coverage, gaps)) in seasons {
+                        println!(
+                            "\t\t{}: {}",
+                            season.blue(),
+                            coverage.describe(language.name)
+                        );
+                        print_gaps(&gaps, 3);
+                    }
+                }
+            }
+            MediaType::Unknown => unreachable!(),
+        }
+    }
+}
+
+fn print_gaps(gaps: &[Gap], indent: usize) {
+    let indent = "\t".repeat(indent);
+    for gap in gaps {
+        if gap.image_based {
+            println!("{}{} {}", indent, gap.name, "(image-based only)".yellow());
+        } else if gap.forced_only {
+            println!("{}{} {}", indent, gap.name, "(forced only)".yellow());
+        } else if gap.no_subtitles {
+            println!("{}{} {}", indent, gap.name, "(no subtitles)".red());
+        } else {
+            println!("{}{}", indent, gap.name);
+        }
+    }
+}
+
+// Coverage of each directory (i.e., season) within a series, by directory name
+fn season_coverage(series_path: &Path, code: &str) -> BTreeMap<String, (Coverage, Vec<Gap>)> {
+    let mut dirs: Vec<PathBuf> = vec![series_path.to_path_buf()];
+    dirs.extend(
+        fs::read_dir(series_path)
+            .expect("Cannot read directory")
+            .map(|e| e.expect("Cannot retreive file information").path())
+            .filter(|p| p.is_dir()),
+    );
+    dirs.into_iter()
+        .map(|d| {
+            let name = if d == series_path {
+                "(Series directory)".to_string()
+            } else {
+                d.file_name().unwrap().to_string_lossy().to_string()
+            };
+            let media = media_files(&d, false);
+            (name, media_coverage(&media, code))
+        })
+        .filter(|(_, (coverage, _))| coverage.total > 0)
+        .collect()
+}
+
+// Coverage of every media file under a directory
+fn directory_coverage(dir: &Path, code: &str) -> (Coverage, Vec<Gap>) {
+    media_coverage(&media_files(dir, true), code)
+}
+
+fn media_files(dir: &Path, recursive: bool) -> Vec<PathBuf> {
+    let files = if recursive {
+        dir::list_files(dir).unwrap_or_else(|_| panic!("Cannot read directory: {:?}", dir))
+    } else {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .expect("Cannot read directory")
+            .map(|e| e.expect("Cannot retreive file information").path())
+            .collect();
+        files.sort();
+        files
+    };
+    files
+        .into_iter()
+        .filter(|p| path::is_media_file(p) && junk::junk_category(p).is_none())
+        .collect()
+}
+
+fn media_coverage(media: &[PathBuf], code: &str) -> (Coverage, Vec<Gap>) {
+    let mut coverage = Coverage::default();
+    let mut gaps = Vec::new();
+    for p in media {
+        let subtitles = subtitles_for(p);
+        // Forced subtitles do not cover the whole film or episode
+        let full: Vec<_> = subtitles.iter().filter(|s| !s.forced).collect();
+        coverage.total += 1;
+        if !full.is_empty() {
+            coverage.with_any += 1;
+        }
+        let has_text = full.iter().any(|s| s.language == code && !s.image_based);
+        let has_image = full.iter().any(|s| s.language == code && s.image_based);
+        if has_text || has_image {
+            coverage.with_language += 1;
+        }
+        if has_text {
+            continue;
+        }
+        if has_image {
+            coverage.image_only += 1;
+        }
+        gaps.push(Gap {
+            name: p.file_name().unwrap().to_string_lossy().to_string(),
+            no_subtitles: full.is_empty(),
+            image_based: has_image,
+            forced_only: !has_image && subtitles.iter().any(|s| s.language == code),
+        });
+    }
+    (coverage, gaps)
+}
+
+// The subtitles alongside a media file
+fn subtitles_for(media: &Path) -> Vec<Subtitle> {
+    let stem = match media.file_stem().and_then(|s| s.to_str()) {
+        Some(stem) => stem,
+        None => return Vec::new(),
+    };
+    let parent = match media.parent() {
+        Some(parent) => parent,
+        None => return Vec::new(),
+    };
+    fs::read_dir(parent)
+        .expect("Cannot read directory")
+        .filter_map(|e| {
+            let p = e.ok()?.path();
+            let sub_name = SubtitleName::parse(p.file_name()?.to_str()?)?;
+            if sub_name.fname != stem {
+                return None;
+            }
+            let lang = sub_name.language();
+            Some(Subtitle {
+                language: locale::find_language(lang)
+                    .map(locale::preferred_code)
+                    .unwrap_or(lang)
+                    .to_string(),
+                image_based: path::is_bitmap_subtitle(&p),
+                forced: sub_name.forced,
+            })
+        })
+        .collect()
+}
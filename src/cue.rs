@@ -0,0 +1,279 @@
+// This file provides the pieces shared by the subtitle file parsers: cues,
+// timestamps, and problems found while parsing
+use std::fmt;
+use std::path::Path;
+
+use super::ass;
+use super::path;
+use super::smi;
+use super::srt;
+use super::vtt;
+
+// Text subtitle formats we can parse
+#[derive(PartialEq, Clone, Copy)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+    Ssa,
+    Smi,
+}
+
+impl SubtitleFormat {
+    pub fn from_path(p: &Path) -> Option<Self> {
+        Self::from_extension(path::get_extension_from_filename(p)?)
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" => Some(SubtitleFormat::Vtt),
+            "ass" => Some(SubtitleFormat::Ass),
+            "ssa" => Some(SubtitleFormat::Ssa),
+            "smi" => Some(SubtitleFormat::Smi),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+            SubtitleFormat::Ssa => "ssa",
+            SubtitleFormat::Smi => "smi",
+        }
+    }
+
+    // Parse a file in this format into its cues, along with any problems
+    // found in it
+    pub fn parse(&self, contents: &str) -> (Vec<Cue>, Vec<Problem>) {
+        match self {
+            SubtitleFormat::Srt => srt::parse(contents),
+            SubtitleFormat::Vtt => vtt::parse(contents),
+            SubtitleFormat::Ass | SubtitleFormat::Ssa => ass::parse(contents),
+            SubtitleFormat::Smi => smi::parse(contents),
+        }
+    }
+
+    // Rewrite every time in a file in this format with `f`, leaving
+    // everything else as it is
+    pub fn retime(&self, contents: &str, f: &dyn Fn(i64) -> i64) -> String {
+        match self {
+            SubtitleFormat::Srt => srt::retime(contents, f),
+            SubtitleFormat::Vtt => vtt::retime(contents, f),
+            SubtitleFormat::Ass | SubtitleFormat::Ssa => ass::retime(contents, f),
+            SubtitleFormat::Smi => smi::retime(contents, f),
+        }
+    }
+}
+
+impl fmt::Display for SubtitleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SubtitleFormat::Srt => "SRT",
+            SubtitleFormat::Vtt => "WebVTT",
+            SubtitleFormat::Ass => "ASS",
+            SubtitleFormat::Ssa => "SSA",
+            SubtitleFormat::Smi => "SAMI",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// A single subtitle, shown between two times (in milliseconds)
+#[derive(Clone)]
+pub struct Cue {
+    pub start: i64,
+    pub end: i64,
+    // The line of the file on which the cue's timestamps were found
+    pub line: usize,
+    // The lines of text, with markup as written in the file
+    pub text: Vec<String>,
+    // WebVTT cue settings (e.g., "align:start line:0").  Empty when there
+    // are none, and for other formats
+    pub settings: String,
+    // The name of the cue's ASS/SSA style.  Empty for the default style, and
+    // for other formats
+    pub style: String,
+}
+
+// Something wrong with a subtitle file, found at a given line
+pub struct Problem {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Problem {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+// Split a file's contents into lines, dropping any byte order mark
+pub fn lines(contents: &str) -> Vec<&str> {
+    contents.trim_start_matches('\u{feff}').lines().collect()
+}
+
+// Split lines into blocks separated by blank lines, along with the (1-based)
+// line number each block starts on
+pub fn blocks<'a>(lines: &[&'a str]) -> Vec<(usize, Vec<&'a str>)> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(usize, Vec<&str>)> = None;
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            if let Some(block) = current.take() {
+                blocks.push(block);
+            }
+        } else {
+            current
+                .get_or_insert_with(|| (i + 1, Vec::new()))
+                .1
+                .push(line);
+        }
+    }
+    if let Some(block) = current {
+        blocks.push(block);
+    }
+    blocks
+}
+
+// Check that cues are in order, do not overlap, and do not end before they
+// start
+pub fn check_timing(cues: &[Cue]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut prev: Option<&Cue> = None;
+    for cue in cues {
+        if cue.end < cue.start {
+            problems.push(Problem::new(
+                cue.line,
+                format!(
+                    "Cue ends ({}) before it starts ({})",
+                    format_timestamp(cue.end, ','),
+                    format_timestamp(cue.start, ',')
+                ),
+            ));
+        } else if cue.end == cue.start {
+            problems.push(Problem::new(cue.line, "Cue has zero duration"));
+        }
+        if let Some(prev) = prev {
+            if cue.start < prev.start {
+                problems.push(Problem::new(
+                    cue.line,
+                    format!(
+                        "Cue is out of order: starts before the previous cue (line {})",
+                        prev.line
+                    ),
+                ));
+            } else if cue.start < prev.end {
+                problems.push(Problem::new(
+                    cue.line,
+                    format!("Cue overlaps the previous cue (line {})", prev.line),
+                ));
+            }
+        }
+        prev = Some(cue);
+    }
+    problems
+}
+
+// Parse "HH:MM:SS,mmm" (or with a '.' before the milliseconds) into
+// milliseconds.  Hours are optional when `optional_hours` is set, as in WebVTT
+pub fn parse_timestamp(s: &str, optional_hours: bool) -> Option<i64> {
+    let (hms, ms) = s.trim().rsplit_once([',', '.'])?;
+    if ms.len() != 3 {
+        return None;
+    }
+    let ms = parse_digits(ms)?;
+    let parts: Vec<&str> = hms.split(':').collect();
+    let (h, m, s) = match parts.as_slice() {
+        [h, m, s] => (parse_digits(h)?, *m, *s),
+        [m, s] if optional_hours => (0, *m, *s),
+        _ => return None,
+    };
+    if m.len() != 2 || s.len() != 2 {
+        return None;
+    }
+    let (m, s) = (parse_digits(m)?, parse_digits(s)?);
+    if m >= 60 || s >= 60 {
+        return None;
+    }
+    // Hours with too many digits would overflow
+    h.checked_mul(3_600_000)?
+        .checked_add((m * 60 + s) * 1000 + ms)
+}
+
+fn parse_digits(s: &str) -> Option<i64> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+// Format milliseconds as "HH:MM:SS,mmm", with the given separator before the
+// milliseconds.  Negative times are clamped to zero
+pub fn format_timestamp(ms: i64, sep: char) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1000) % 60,
+        sep,
+        ms % 1000
+    )
+}
+
+// Apply `f` to each line of a file, keeping the original line endings
+pub fn map_lines(contents: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut out = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let (text, ending) = match line.strip_suffix("\r\n") {
+            Some(text) => (text, "\r\n"),
+            None => match line.strip_suffix('\n') {
+                Some(text) => (text, "\n"),
+                None => (line, ""),
+            },
+        };
+        out.push_str(&f(text));
+        out.push_str(ending);
+    }
+    out
+}
+
+// Rewrite the times of a timing line with `f`, using `sep` before the
+// milliseconds.  Returns None if the line is not a valid timing line
+pub fn retime_timing_line(
+    line: &str,
+    optional_hours: bool,
+    sep: char,
+    f: &dyn Fn(i64) -> i64,
+) -> Option<String> {
+    let (start, end, settings) = split_timing_line(line)?;
+    let start = parse_timestamp(start, optional_hours)?;
+    let end = parse_timestamp(end, optional_hours)?;
+    let mut line = format!(
+        "{} --> {}",
+        format_timestamp(f(start), sep),
+        format_timestamp(f(end), sep)
+    );
+    if !settings.is_empty() {
+        line.push(' ');
+        line.push_str(settings);
+    }
+    Some(line)
+}
+
+// Split a timing line ("start --> end [settings]") into its parts
+pub fn split_timing_line(line: &str) -> Option<(&str, &str, &str)> {
+    let (start, rest) = line.split_once("-->")?;
+    let rest = rest.trim_start();
+    let (end, settings) = match rest.split_once(char::is_whitespace) {
+        Some((end, settings)) => (end, settings.trim()),
+        None => (rest, ""),
+    };
+    Some((start.trim(), end.trim(), settings))
+}
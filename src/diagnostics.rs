@@ -0,0 +1,340 @@
+// This file provides diagnostics for names which nearly match one of the
+// naming regexes in constants.rs, explaining what is wrong with them and
+// suggesting a corrected name
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use colored::*;
+
+use super::constants;
+use super::locale;
+
+pub struct Diagnostic {
+    pub problems: Vec<String>,
+    // The corrected name, if fixing the problems we found makes it valid
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(problems: Vec<String>, fixed: String, pattern: &Regex) -> Self {
+        let suggestion = if pattern.is_match(&fixed) {
+            Some(fixed)
+        } else {
+            None
+        };
+        Diagnostic {
+            problems,
+            suggestion,
+        }
+    }
+
+    // Print the diagnostic beneath an existing line of output
+    pub fn print(&self, indent: usize) {
+        for line in self.lines(indent) {
+            println!("{}", line);
+        }
+    }
+
+    pub fn eprint(&self, indent: usize) {
+        for line in self.lines(indent) {
+            eprintln!("{}", line);
+        }
+    }
+
+    fn lines(&self, indent: usize) -> Vec<String> {
+        let indent = "\t".repeat(indent);
+        let mut lines: Vec<_> = self
+            .problems
+            .iter()
+            .map(|problem| format!("{}- {}", indent, problem.italic()))
+            .collect();
+        if let Some(suggestion) = &self.suggestion {
+            lines.push(format!(
+                "{}{}{}",
+                indent,
+                "Suggested name: ".italic(),
+                suggestion.green()
+            ));
+        }
+        lines
+    }
+}
+
+lazy_static! {
+    static ref FILM_NEAR_RE: Regex = Regex::new(r"^(?P<title>.*[^\s._\(\[])(?P<sep>[\s._]*)(?P<open>[\(\[]?)(?P<year>(19|20)\d{2})(?P<close>[\)\]]?)(?P<rest>.*)$").unwrap();
+    static ref SEASON_NEAR_RE: Regex = Regex::new(r"^(?i)(?P<prefix>season|s)(?P<sep>[\s._-]*)(?P<snum>\d+)((?P<namesep>\s*-\s*|\s+)(?P<sname>.+))?$").unwrap();
+    static ref EP_ALT_NUM_RE: Regex = Regex::new(r"\b(?P<snum>\d{1,2})x(?P<epnum>\d{2,})\b").unwrap();
+    static ref EP_CODE_RE: Regex = Regex::new(r"(?i)\bs(?P<snum>\d+)e(?P<epnum>\d+)").unwrap();
+    static ref EP_NEAR_RE: Regex = Regex::new(r"^(?P<sname>.*?\S)(?P<sep>\s*-?\s*)(?P<code>S\d+E\d+)(?P<rest>.*)$").unwrap();
+    static ref SUB_NEAR_RE: Regex = Regex::new(r"^(?P<fname>.+?)(?P<sep>[._\s])(?P<locale>[A-Za-z]+(?:[-_][A-Za-z]+)?)$").unwrap();
+}
+
+// Diagnose a film name (i.e., without extension), which should look like
+// "Name (Year)"
+pub fn diagnose_film(name: &str) -> Option<Diagnostic> {
+    if constants::FILM_RE.is_match(name) {
+        return None;
+    }
+    let caps = match FILM_NEAR_RE.captures(name) {
+        Some(caps) => caps,
+        None => {
+            return Some(Diagnostic {
+                problems: vec!["Cannot find the year of the film".to_string()],
+                suggestion: None,
+            })
+        }
+    };
+    let mut problems = Vec::new();
+    let mut title = caps["title"].to_string();
+    if !title.contains(' ') && title.contains(['.', '_']) {
+        problems.push("Uses dots or underscores instead of spaces".to_string());
+        title = title.replace(['.', '_'], " ");
+    }
+    match (&caps["open"], &caps["close"]) {
+        ("(", ")") => {}
+        ("[", "]") => problems.push("Year is in square brackets".to_string()),
+        _ => problems.push("Year is not in parentheses".to_string()),
+    }
+    if &caps["sep"] != " " {
+        problems.push("Expected a single space before the year".to_string());
+    }
+    if !caps["rest"].is_empty() {
+        problems.push(format!(
+            "Unexpected text after the year: \"{}\"",
+            &caps["rest"]
+        ));
+    }
+    let fixed = format!("{} ({})", title.trim(), &caps["year"]);
+    Some(Diagnostic::new(problems, fixed, &constants::FILM_RE))
+}
+
+// Diagnose a season directory name, which should look like "Season 01" or
+// "Season 01 - Name"
+pub fn diagnose_season(name: &str) -> Option<Diagnostic> {
+    if constants::SEASON_RE.is_match(name) {
+        return None;
+    }
+    let caps = SEASON_NEAR_RE.captures(name)?;
+    let mut problems = Vec::new();
+    if &caps["prefix"] != "Season" {
+        problems.push(format!(
+            "Expected \"Season\", found \"{}\"",
+            &caps["prefix"]
+        ));
+    }
+    if &caps["sep"] != " " {
+        problems.push("Expected a single space after \"Season\"".to_string());
+    }
+    let snum = caps["snum"].parse::<isize>().unwrap();
+    if caps["snum"].len() < 2 {
+        problems.push("Season number should have at least two digits".to_string());
+    }
+    let mut fixed = format!("Season {:02}", snum);
+    if let Some(sname) = caps.name("sname") {
+        if &caps["namesep"] != " - " {
+            problems.push("Expected \" - \" between the season number and name".to_string());
+        }
+        fixed.push_str(" - ");
+        fixed.push_str(sname.as_str());
+    }
+    Some(Diagnostic::new(problems, fixed, &constants::SEASON_RE))
+}
+
+// Diagnose an episode file name, which should look like
+// "Series - S01E01 - Title.ext"
+pub fn diagnose_episode(name: &str) -> Option<Diagnostic> {
+    if constants::EP_RE.is_match(name) {
+        return None;
+    }
+    let (stem, ext) = split_extension(name)?;
+    let mut problems = Vec::new();
+    let mut fixed = stem.to_string();
+    if !fixed.contains(' ') && fixed.contains(['.', '_']) {
+        problems.push("Uses dots or underscores instead of spaces".to_string());
+        fixed = fixed.replace(['.', '_'], " ");
+    }
+    if EP_ALT_NUM_RE.is_match(&fixed) {
+        problems.push("Uses \"1x01\" numbering rather than \"S01E01\"".to_string());
+        fixed = EP_ALT_NUM_RE
+            .replace(&fixed, "S${snum}E${epnum}")
+            .to_string();
+    }
+    let code = match EP_CODE_RE.captures(&fixed) {
+        Some(caps) => caps,
+        None => {
+            return Some(Diagnostic {
+                problems: vec!["Cannot find the season and episode number".to_string()],
+                suggestion: None,
+            })
+        }
+    };
+    let code_str = code.get(0).unwrap().as_str().to_string();
+    if code_str.contains(['s', 'e']) {
+        problems.push(format!(
+            "Episode number \"{}\" should be upper case",
+            code_str
+        ));
+    }
+    let snum = code["snum"].parse::<isize>().unwrap();
+    let epnum = code["epnum"].parse::<isize>().unwrap();
+    if code["snum"].len() < 2 || code["epnum"].len() < 2 {
+        problems.push("Season and episode numbers should have at least two digits".to_string());
+    }
+    let new_code = format!("S{:02}E{:02}", snum, epnum);
+    fixed = fixed.replacen(&code_str, &new_code, 1);
+    if let Some(caps) = EP_NEAR_RE.captures(&fixed) {
+        let sep = &caps["sep"];
+        if sep != " - " {
+            problems.push(if sep.contains('-') {
+                "Expected a space either side of the hyphen before the episode number".to_string()
+            } else {
+                "Expected \" - \" before the episode number".to_string()
+            });
+        }
+        let rest = caps["rest"].trim_start_matches(|c: char| c == '-' || c.is_whitespace());
+        if !rest.is_empty() && caps["rest"] != format!(" - {}", rest) {
+            problems.push("Expected \" - \" before the episode title".to_string());
+        }
+        let mut renamed = format!("{} - {}", &caps["sname"], &caps["code"]);
+        if !rest.is_empty() {
+            renamed.push_str(" - ");
+            renamed.push_str(rest);
+        }
+        fixed = renamed;
+    } else {
+        problems.push("Cannot find the series name".to_string());
+    }
+    let ext = lowercase_extension(ext, &mut problems);
+    fixed = format!("{}.{}", fixed, ext);
+    Some(Diagnostic::new(problems, fixed, &constants::EP_RE))
+}
+
+// Diagnose a subtitle file name, which should look like "Name.locale.ext".
+// Unlike the other diagnostics, this can find problems with names that match
+// the subtitle regex, as it will accept any two- or three-letter language code
+pub fn diagnose_subtitle(name: &str) -> Option<Diagnostic> {
+    let (stem, ext) = split_extension(name)?;
+    let mut problems = Vec::new();
+    // Remove any flags (e.g., "forced") from after the locale
+    let mut stem = stem;
+    let mut flags = Vec::new();
+    while let Some((rest, flag)) = stem.rsplit_once('.') {
+        let lower = flag.to_lowercase();
+        let new_flag = match lower.as_str() {
+            "hi" => "sdh",
+            f => match constants::SUBTITLE_FLAGS.iter().find(|g| **g == f) {
+                Some(g) => g,
+                None => break,
+            },
+        };
+        if new_flag != flag {
+            problems.push(format!(
+                "Subtitle flag \"{}\" should be written as \"{}\"",
+                flag, new_flag
+            ));
+        }
+        flags.insert(0, new_flag);
+        stem = rest;
+    }
+    let caps = match SUB_NEAR_RE.captures(stem) {
+        Some(caps) => caps,
+        None => {
+            return Some(Diagnostic {
+                problems: vec!["Cannot find the language code of the subtitle".to_string()],
+                suggestion: None,
+            })
+        }
+    };
+    let locale = &caps["locale"];
+    let (lang, region) = match locale.split_once(['-', '_']) {
+        Some((lang, region)) => (lang, Some(region)),
+        None => (locale, None),
+    };
+    let lower = lang.to_lowercase();
+    let suggested = locale::suggest_language(&lower);
+    // Only trust the last word of the name to be a language if it is separated
+    // by a dot or is a language we recognise
+    let known = suggested.is_some() || locale::is_preferred_language(&lower);
+    let plausible = if &caps["sep"] == "." {
+        known || (2..=3).contains(&lang.len())
+    } else {
+        known
+    };
+    if !plausible {
+        return Some(Diagnostic {
+            problems: vec!["Cannot find the language code of the subtitle".to_string()],
+            suggestion: None,
+        });
+    }
+    if &caps["sep"] != "." {
+        problems.push("Language code should be separated from the name by a dot".to_string());
+    }
+    // Whether we know what the corrected name should be
+    let mut fixable = true;
+    let new_lang = match suggested {
+        Some(code) => {
+            problems.push(format!(
+                "Language \"{}\" should be written as \"{}\" ({})",
+                lang,
+                code,
+                locale::language_name(code).unwrap_or(code)
+            ));
+            code.to_string()
+        }
+        None if known => {
+            if lower != lang {
+                problems.push(format!("Language code \"{}\" should be lower case", lang));
+            }
+            lower
+        }
+        None => {
+            problems.push(format!("Unknown language code \"{}\"", lang));
+            fixable = false;
+            lower
+        }
+    };
+    let mut new_locale = new_lang;
+    if let Some(region) = region {
+        if locale.contains('_') {
+            problems.push("Region should be separated from the language by a hyphen".to_string());
+        }
+        let mut new_region = region.to_uppercase();
+        if region != new_region {
+            problems.push(format!("Region \"{}\" should be upper case", region));
+        }
+        if let Some(r) = locale::suggest_region(&new_region) {
+            problems.push(format!(
+                "Region \"{}\" should be written as \"{}\"",
+                region, r
+            ));
+            new_region = r.to_string();
+        } else if !locale::is_region(&new_region) {
+            problems.push(format!("Unknown region \"{}\"", region));
+            fixable = false;
+        }
+        new_locale = format!("{}-{}", new_locale, new_region);
+    }
+    let ext = lowercase_extension(ext, &mut problems);
+    if problems.is_empty() {
+        return None;
+    }
+    let flags: String = flags.iter().map(|f| format!(".{}", f)).collect();
+    let fixed = format!("{}.{}{}.{}", &caps["fname"], new_locale, flags, ext);
+    let mut diagnostic = Diagnostic::new(problems, fixed, &constants::SUB_RE);
+    if !fixable {
+        diagnostic.suggestion = None;
+    }
+    Some(diagnostic)
+}
+
+fn split_extension(name: &str) -> Option<(&str, &str)> {
+    name.rsplit_once('.')
+}
+
+fn lowercase_extension(ext: &str, problems: &mut Vec<String>) -> String {
+    let lower = ext.to_lowercase();
+    if lower != ext {
+        problems.push(format!("Extension \"{}\" should be lower case", ext));
+    }
+    lower
+}
@@ -1,13 +1,13 @@
 // This file provides functions used for dynamically locating the media directory
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
 
 // Films and series directory names
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum MediaType {
     Film,
     Series,
     Unknown,
-    Impossible,
 }
 
 // https://stackoverflow.com/a/65040451
@@ -17,9 +17,42 @@ impl MediaType {
             MediaType::Film => "Films",
             MediaType::Series => "Series",
             MediaType::Unknown => "<Unknown>",
-            MediaType::Impossible => "<Impossible>",
         }
     }
+
+    // The libraries to look in for a media type, with both libraries being
+    // used when the media type is not known
+    pub fn libraries(&self) -> Vec<MediaType> {
+        match self {
+            MediaType::Film => vec![MediaType::Film],
+            MediaType::Series => vec![MediaType::Series],
+            MediaType::Unknown => vec![MediaType::Film, MediaType::Series],
+        }
+    }
+}
+
+// Recursively list every file under a directory, in sorted order
+// TODO: use walkdir or something (see jakewilliami/lsext)
+pub fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fn recurse_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        if dir.is_dir() {
+            let mut entries = fs::read_dir(dir)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    recurse_files(&path, files)?;
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    recurse_files(dir, &mut files)?;
+    Ok(files)
 }
 
 // Conditionally compiling functions for obtaining media directoried
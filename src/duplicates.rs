@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use super::constants;
+use super::dir::MediaType;
+use super::path;
+
+pub fn check_duplicate_episodes(dirname: &Path) {
+    let mut series_dir = dirname.to_path_buf();
+    series_dir.push(MediaType::Series.as_str());
+    // Get series available
+    let mut series: Vec<_> = fs::read_dir(&series_dir)
+        .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", series_dir))
+        .map(|e| e.expect("Cannot retreive file information").path())
+        .filter(|p| p.is_dir())
+        .collect();
+    series.sort();
+    // Search through series
+    for path in series {
+        let series_name = path.file_name().unwrap().to_str().unwrap().to_string();
+        // Map of <(season number, episode number) -> candidate files>
+        let mut episodes_map = BTreeMap::<(isize, isize), Vec<PathBuf>>::new();
+        let mut season_dirs: Vec<_> = fs::read_dir(&path)
+            .expect("Cannot read directory")
+            .map(|e| e.expect("Cannot retreive file information").path())
+            .filter(|p| {
+                p.is_dir()
+                    && constants::SEASON_RE.is_match(p.file_name().unwrap().to_str().unwrap())
+            })
+            .collect();
+        season_dirs.sort();
+        // Search through series' seasons
+        for season_dir in season_dirs {
+            for ep in fs::read_dir(&season_dir)
+                .expect("Cannot read directory")
+                .map(|e| e.expect("Cannot retrieve file information").path())
+            {
+                if !path::is_media_file(&ep) {
+                    continue;
+                }
+                let ep_name = ep.file_name().unwrap().to_str().unwrap();
+                if let Some(caps) = constants::EP_RE.captures(ep_name) {
+                    let season_num = caps["snum"].parse::<isize>().unwrap();
+                    let ep_num = caps["epnum"].parse::<isize>().unwrap();
+                    episodes_map
+                        .entry((season_num, ep_num))
+                        .or_default()
+                        .push(ep.clone());
+                }
+            }
+        }
+        // Display results
+        let duplicates: Vec<_> = episodes_map.iter().filter(|(_, v)| v.len() > 1).collect();
+        if duplicates.is_empty() {
+            continue;
+        }
+        println!("{}", series_name.blue().bold());
+        for ((season_num, ep_num), candidates) in duplicates {
+            println!(
+                "\t{}",
+                format!("Season {}, Episode {}", season_num, ep_num).blue()
+            );
+            let mut candidates = candidates.clone();
+            candidates.sort();
+            for candidate in candidates {
+                let size = fs::metadata(&candidate).map(|m| m.len()).unwrap_or(0);
+                let ext = path::get_extension_from_filename(&candidate).unwrap_or("");
+                println!(
+                    "\t\t{} ({}, {})",
+                    candidate.file_name().unwrap().to_str().unwrap(),
+                    format_size(size).bold(),
+                    ext.italic()
+                );
+            }
+        }
+    }
+}
+
+// Display a number of bytes in a human-readable form
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,206 @@
+// This file provides a reader for EBML, the binary format Matroska (and so
+// WebM) files are written in.  A file is a tree of elements, each written as
+// an ID and a size (both variable-length integers) followed by its data.
+//
+// See RFC 8794 (https://www.rfc-editor.org/rfc/rfc8794)
+use std::convert::TryInto;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+// The largest element we will read into memory.  Only master elements (e.g.,
+// a whole cluster) should be larger than this, and they are read piece by
+// piece
+const MAX_ELEMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+// The start of an element
+pub struct Header {
+    pub id: u32,
+    // The size of the element's data, or None if it is unknown (as allowed for
+    // master elements written by streaming muxers)
+    pub size: Option<u64>,
+    // The position of the element's data in the file
+    pub start: u64,
+}
+
+impl Header {
+    // The position of the end of the element, if its size is known
+    pub fn end(&self) -> Option<u64> {
+        self.size.map(|size| self.start + size)
+    }
+}
+
+// Reads elements from a file, keeping track of the position in it
+pub struct Reader<R: Read + Seek> {
+    inner: BufReader<R>,
+    pos: u64,
+    len: u64,
+}
+
+impl<R: Read + Seek> Reader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let len = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(0))?;
+        Ok(Reader {
+            inner: BufReader::new(inner),
+            pos: 0,
+            len,
+        })
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    // The length of the whole file
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    // Read the header of the next element, or None at the end of the file
+    pub fn read_header(&mut self) -> io::Result<Option<Header>> {
+        if self.pos >= self.len {
+            return Ok(None);
+        }
+        let id = self.read_vint(true)?;
+        let size = self.read_vint(false)?;
+        // A size with every value bit set means the size is unknown
+        let size = if size.0 == (1 << (7 * size.1)) - 1 {
+            None
+        } else {
+            Some(size.0)
+        };
+        Ok(Some(Header {
+            id: id.0 as u32,
+            size,
+            start: self.pos,
+        }))
+    }
+
+    // Read the data of an element whose header has just been read
+    pub fn read_data(&mut self, header: &Header) -> io::Result<Vec<u8>> {
+        let size = match header.size {
+            Some(size) if size <= MAX_ELEMENT_SIZE => size,
+            _ => {
+                return Err(invalid_data(format!(
+                    "element {:X} is too large to read",
+                    header.id
+                )))
+            }
+        };
+        if header.start + size > self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("element {:X} runs past the end of the file", header.id),
+            ));
+        }
+        let mut data = vec![0; size as usize];
+        self.inner.read_exact(&mut data)?;
+        self.pos += size;
+        Ok(data)
+    }
+
+    // Skip over the data of an element whose header has just been read
+    pub fn skip(&mut self, header: &Header) -> io::Result<()> {
+        match header.end() {
+            Some(end) => self.seek(end),
+            None => Err(invalid_data(format!(
+                "cannot skip element {:X} of unknown size",
+                header.id
+            ))),
+        }
+    }
+
+    pub fn seek(&mut self, pos: u64) -> io::Result<()> {
+        self.inner.seek_relative(pos as i64 - self.pos as i64)?;
+        self.pos = pos;
+        Ok(())
+    }
+
+    // Read a variable-length integer, returning it along with its length in
+    // bytes.  IDs keep their length marker; sizes do not
+    fn read_vint(&mut self, keep_marker: bool) -> io::Result<(u64, u32)> {
+        let mut first = [0];
+        self.inner.read_exact(&mut first)?;
+        let len = first[0].leading_zeros() + 1;
+        if len > 8 {
+            return Err(invalid_data(format!(
+                "invalid variable-length integer at byte {}",
+                self.pos
+            )));
+        }
+        let mut value = if keep_marker {
+            first[0] as u64
+        } else {
+            (first[0] & (0x7F >> (len - 1))) as u64
+        };
+        let mut rest = [0; 7];
+        self.inner.read_exact(&mut rest[..len as usize - 1])?;
+        for byte in &rest[..len as usize - 1] {
+            value = (value << 8) | *byte as u64;
+        }
+        self.pos += len as u64;
+        Ok((value, len))
+    }
+}
+
+// The child elements of a master element's data, as pairs of IDs and data.
+// Stops at the first child which does not fit
+pub fn children(mut data: &[u8]) -> Vec<(u32, &[u8])> {
+    let mut children = Vec::new();
+    while !data.is_empty() {
+        let (id, id_len) = match vint(data) {
+            Some((_, len)) => (uint(&data[..len]) as u32, len),
+            None => break,
+        };
+        let (size, size_len) = match vint(&data[id_len..]) {
+            Some((size, len)) => (size as usize, len),
+            None => break,
+        };
+        let start = id_len + size_len;
+        if size > data.len() - start {
+            break;
+        }
+        children.push((id, &data[start..start + size]));
+        data = &data[start + size..];
+    }
+    children
+}
+
+// Read a variable-length integer (without its length marker) from the start
+// of some data, returning it along with its length in bytes
+pub fn vint(data: &[u8]) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 || data.len() < len {
+        return None;
+    }
+    let value = data[1..len]
+        .iter()
+        .fold((first & (0x7F >> (len - 1))) as u64, |value, byte| {
+            (value << 8) | *byte as u64
+        });
+    Some((value, len))
+}
+
+pub fn uint(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+pub fn float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+// A string element, which may be padded with null bytes
+pub fn string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+pub fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,253 @@
+// This file provides detection of the character encoding of subtitle files,
+// and conversion of them to UTF-8.
+//
+// Without a byte order mark, we cannot reliably tell the single-byte
+// encodings apart, so anything which is not valid UTF-8 (or UTF-16) is
+// treated as Windows-1252 if it uses any of the characters Windows-1252 adds
+// in 0x80-0x9F, and ISO-8859-1 otherwise.  Other ISO-8859 variants will
+// decode as ISO-8859-1, which is right for most Western European text.
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use super::path;
+use super::subtitles;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Encoding {
+    Utf8,
+    // UTF-8 with a byte order mark, which is still UTF-8 as far as we care
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+    Iso8859_1,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 (with BOM)",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Windows1252 => "Windows-1252",
+            Encoding::Iso8859_1 => "ISO-8859-1",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Encoding {
+    pub fn is_utf8(&self) -> bool {
+        matches!(self, Encoding::Utf8 | Encoding::Utf8Bom)
+    }
+}
+
+pub fn detect(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Encoding::Utf8Bom;
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Encoding::Utf16Le;
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Encoding::Utf16Be;
+    }
+    if let Some(encoding) = detect_utf16_without_bom(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Encoding::Utf8;
+    }
+    if bytes.iter().any(|b| (0x80..=0x9F).contains(b)) {
+        Encoding::Windows1252
+    } else {
+        Encoding::Iso8859_1
+    }
+}
+
+// Text which is mostly ASCII will have a zero byte in every other position
+// when encoded as UTF-16
+fn detect_utf16_without_bom(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 4 || bytes.len() % 2 != 0 {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let even_zeros = bytes.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd_zeros * 10 >= pairs * 9 && even_zeros == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even_zeros * 10 >= pairs * 9 && odd_zeros == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+pub fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+        Encoding::Utf8Bom => String::from_utf8_lossy(&bytes[3..]).to_string(),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let has_bom = bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]);
+            let bytes = if has_bom { &bytes[2..] } else { bytes };
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| {
+                    if encoding == Encoding::Utf16Le {
+                        u16::from_le_bytes([c[0], c[1]])
+                    } else {
+                        u16::from_be_bytes([c[0], c[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        Encoding::Windows1252 => bytes.iter().map(|b| windows_1252_char(*b)).collect(),
+        Encoding::Iso8859_1 => bytes.iter().map(|b| *b as char).collect(),
+    }
+}
+
+// Encode text, the reverse of `decode`.  UTF-16 is always written with a byte
+// order mark, and characters the single-byte encodings cannot represent are
+// written as "?"
+pub fn encode(contents: &str, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Utf8 => contents.as_bytes().to_vec(),
+        Encoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], contents.as_bytes()].concat(),
+        Encoding::Utf16Le => {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(contents.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        }
+        Encoding::Utf16Be => {
+            let mut bytes = vec![0xFE, 0xFF];
+            bytes.extend(contents.encode_utf16().flat_map(u16::to_be_bytes));
+            bytes
+        }
+        Encoding::Windows1252 => contents.chars().map(windows_1252_byte).collect(),
+        Encoding::Iso8859_1 => contents
+            .chars()
+            .map(|c| u8::try_from(c).unwrap_or(b'?'))
+            .collect(),
+    }
+}
+
+// Read a text file, decoding it from whichever encoding it appears to use
+pub fn read_to_string(p: &Path) -> io::Result<(String, Encoding)> {
+    let bytes = fs::read(p)?;
+    let encoding = detect(&bytes);
+    Ok((decode(&bytes, encoding), encoding))
+}
+
+// Windows-1252 is ISO-8859-1 with printable characters in place of most of
+// the C1 control codes
+const WINDOWS_1252_C1: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+fn windows_1252_char(b: u8) -> char {
+    match b {
+        0x80..=0x9F => WINDOWS_1252_C1[(b - 0x80) as usize],
+        _ => b as char,
+    }
+}
+
+fn windows_1252_byte(c: char) -> u8 {
+    match WINDOWS_1252_C1.iter().position(|c1| *c1 == c) {
+        Some(i) => 0x80 + i as u8,
+        None => match u8::try_from(c) {
+            Ok(b) if !(0x80..=0x9F).contains(&b) => b,
+            _ => b'?',
+        },
+    }
+}
+
+// Report subtitle files which are not encoded as UTF-8.  With `fix`, rewrite
+// them as UTF-8, keeping the original alongside with a ".bak" extension
+pub fn check_subtitle_encodings(paths: &[PathBuf], fix: bool) {
+    let mut not_utf8 = 0;
+    let mut fixed = 0;
+    for sub in subtitles::find_subtitles(paths) {
+        // Image-based subtitles have no text to encode
+        if path::is_bitmap_subtitle(&sub) {
+            continue;
+        }
+        let (contents, encoding) = match read_to_string(&sub) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", sub, e);
+                continue;
+            }
+        };
+        if encoding.is_utf8() {
+            continue;
+        }
+        not_utf8 += 1;
+        println!(
+            "{}{}{}",
+            sub.display().to_string().bold(),
+            " is encoded as ".italic(),
+            encoding.to_string().yellow()
+        );
+        if !fix {
+            continue;
+        }
+        match write_with_backup(&sub, &contents) {
+            Ok(backup) => {
+                fixed += 1;
+                println!(
+                    "\t{}{}",
+                    "Converted to UTF-8; original kept as ".italic(),
+                    backup.display()
+                );
+            }
+            Err(e) => eprintln!("[ERROR] Cannot convert {:?} to UTF-8: {}", sub, e),
+        }
+    }
+    if fix {
+        println!(
+            "{}{}{}{}",
+            "Converted ".italic(),
+            fixed.to_string().bold(),
+            " of ".italic(),
+            format!("{} subtitle files to UTF-8", not_utf8).italic()
+        );
+    } else {
+        println!(
+            "{}{}",
+            not_utf8.to_string().bold(),
+            " subtitle files are not encoded as UTF-8".italic()
+        );
+    }
+}
+
+// Replace the contents of a file, first copying the original to its backup
+// path.  An existing backup is never overwritten
+pub fn write_with_backup(p: &Path, contents: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+    let backup = backup_path(p);
+    if backup.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("backup {:?} already exists", backup),
+        ));
+    }
+    fs::copy(p, &backup)?;
+    fs::write(p, contents)?;
+    Ok(backup)
+}
+
+// The path to keep a copy of a file at before changing it
+pub fn backup_path(p: &Path) -> PathBuf {
+    let mut backup = p.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
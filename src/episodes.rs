@@ -1,82 +1,197 @@
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-pub fn check_complete_episodes(_dirname: &Path) {
-    todo!();
-    /*
+use colored::*;
+
+use super::constants;
+use super::dir::MediaType;
+use super::manifest::{Manifest, SeriesManifest};
+use super::path;
+
+pub fn check_complete_episodes(dirname: &Path) {
     let mut series_dir = dirname.to_path_buf();
-    series_dir.push(SERIES_DIR_NAME);
-    // Construct a hashmap for storing results
-    let mut missing_eps_map = HashMap::<String, Vec<isize>>::new();
+    series_dir.push(MediaType::Series.as_str());
+    let manifest = Manifest::load(dirname);
     // Get series available
-    let series: Vec<_> = fs::read_dir(&series_dir)
-        .expect(format!("Cannot read directory: {:?}", series_dir).as_str())
+    let mut series: Vec<_> = fs::read_dir(&series_dir)
+        .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", series_dir))
         .map(|e| e.expect("Cannot retreive file information").path())
+        .filter(|p| p.is_dir())
         .collect();
+    series.sort();
     // Search through series
     for path in series {
-        let series_name_outer = &path.file_name().unwrap().to_str().unwrap().to_string();
-        if path.is_dir() {
-            missing_ep_names_map.insert(series_name_outer.to_string(), vec![]);
-            let contents: Vec<_> = fs::read_dir(&path)
+        let series_name = path.file_name().unwrap().to_str().unwrap().to_string();
+        let series_manifest = manifest.get(&path);
+        // Series with absolute numbering are checked across the whole series
+        if let Some(series_manifest) = series_manifest.as_ref().filter(|m| m.absolute) {
+            let episodes: Vec<isize> = absolute_episodes(&path).iter().map(|e| e.num).collect();
+            let expected = series_manifest.expected_total().unwrap_or(0);
+            let missing_eps = missing_numbers(&episodes, expected);
+            if !missing_eps.is_empty() {
+                println!("{}", series_name.blue().bold());
+                println!(
+                    "\t{}",
+                    format!("Missing {}", describe_missing(&missing_eps)).blue()
+                );
+            }
+            continue;
+        }
+        // Map of <season number -> episode numbers>
+        let mut seasons_map = BTreeMap::<isize, Vec<isize>>::new();
+        for season_dir in fs::read_dir(&path)
+            .expect("Cannot read directory")
+            .map(|e| e.expect("Cannot retreive file information").path())
+        {
+            let season_dir_name = season_dir.file_name().unwrap().to_str().unwrap();
+            let season_num = match constants::SEASON_RE.captures(season_dir_name) {
+                Some(caps) => caps["snum"].parse::<isize>().unwrap(),
+                None => continue,
+            };
+            let episodes = seasons_map.entry(season_num).or_default();
+            // Search through episodes
+            for ep in fs::read_dir(&season_dir)
                 .expect("Cannot read directory")
-                .map(|e| {
-                    e.expect("Cannot retreive file information")
-                        .path()
-                        .file_name()
-                        .expect("Cannot get file name from file")
-                        .to_str()
-                        .unwrap()
-                        .to_string()
-                })
-                .collect();
-            // Search through series' seasons
-            for season_dir in contents.iter().filter(|d| season_re.is_match(d)) {
-                let mut season_dir_path = path.clone();
-                season_dir_path.push(&season_dir);
-                let season_content: Vec<_> = fs::read_dir(&season_dir_path)
-                    .expect("Cannot read directory")
-                    .map(|e| {
-                        e.expect("Cannot retrieve file information")
-                            .path()
-                            .file_name()
-                            .expect("Cannot get file name from file")
-                            .to_str()
-                            .unwrap()
-                            .to_string()
-                    })
-                    .collect();
-                // Search through episodes
-                for ep in season_content {
-                    if ep_re.is_match(&ep) {
-                        todo!("In this block, I need to get the episode capture group (3), and do a similar thing to that of the previous one, were we look for max value and find all missing values up to max.  Should print \"Series <series> has all consecutive episodes up to <max_ep>, if we don't find any missing.\"");
-                        let caps = ep_re.captures(&ep).unwrap();
-                        // Check if episode has fifth group (implying it must have a fourth pertaining to ep name)
-                        if caps.get(5).is_none() {
-                            let series_name = caps.get(1).unwrap().as_str().to_string();
-                            let season_num =
-                                caps.get(2).unwrap().as_str().parse::<isize>().unwrap();
-                            if let Some(v) = missing_ep_names_map.get_mut(&series_name) {
-                                (*v).push(season_num);
-                            }
-                            break;
-                        }
-                    }
+                .map(|e| e.expect("Cannot retrieve file information").path())
+            {
+                if !path::is_media_file(&ep) {
+                    continue;
+                }
+                let ep_name = ep.file_name().unwrap().to_str().unwrap();
+                if let Some(caps) = constants::EP_RE.captures(ep_name) {
+                    episodes.push(caps["epnum"].parse::<isize>().unwrap());
                 }
             }
         }
+        // Series without any seasons are reported by the structure check
+        if seasons_map.is_empty() {
+            continue;
+        }
+        let missing = missing_episodes(&seasons_map, series_manifest.as_ref());
+        // Display results
+        if missing.is_empty() {
+            continue;
+        }
+        println!("{}", series_name.blue().bold());
+        for m in missing {
+            println!("\t{}", m.blue());
+        }
+    }
+}
+
+fn missing_episodes(
+    seasons_map: &BTreeMap<isize, Vec<isize>>,
+    manifest: Option<&SeriesManifest>,
+) -> Vec<String> {
+    let mut missing = Vec::new();
+    for (season_num, episodes) in seasons_map {
+        // Without a manifest, we can only find gaps up to the last episode we have
+        let expected = manifest
+            .filter(|m| m.is_complete_season(*season_num))
+            .and_then(|m| m.expected_episodes(*season_num))
+            .unwrap_or(0);
+        let missing_eps = missing_numbers(episodes, expected);
+        if !missing_eps.is_empty() {
+            missing.push(format!(
+                "Season {}: missing {}",
+                season_num,
+                describe_missing(&missing_eps)
+            ));
+        }
+    }
+    // Any seasons after the last one we have, which the manifest knows about
+    if let Some(manifest) = manifest {
+        let last_season = *seasons_map.keys().max().unwrap();
+        for season_num in (last_season + 1)..=manifest.season_count() {
+            if manifest.is_complete_season(season_num) {
+                missing.push(format!("Missing Season {}", season_num));
+            }
+        }
     }
-    // Display results
-    for (s, v) in missing_ep_names_map.iter() {
-        if !v.is_empty() {
-            println!("{}", &s.blue().bold())
-        } /*else {
-              println!("{}", &s.green())
-          }*/
-        let mut w = v.clone();
-        w.sort();
-        for si in w.iter() {
-            println!("\t{}{}", "Season ".blue(), si.to_string().blue())
+    missing
+}
+
+// Numbers from 1 up to the larger of the expected count and the largest number
+// we have, which we do not have
+fn missing_numbers(nums: &[isize], expected: isize) -> Vec<isize> {
+    let max_num = nums.iter().max().copied().unwrap_or(0);
+    (1..=expected.max(max_num))
+        .filter(|i| !nums.contains(i))
+        .collect()
+}
+
+fn describe_missing(missing_eps: &[isize]) -> String {
+    format!(
+        "{} {}",
+        if missing_eps.len() == 1 {
+            "episode"
+        } else {
+            "episodes"
+        },
+        format_ranges(missing_eps)
+    )
+}
+
+// Display sorted numbers compactly, collapsing consecutive runs (e.g., "1-3, 5")
+fn format_ranges(nums: &[isize]) -> String {
+    let mut ranges: Vec<(isize, isize)> = Vec::new();
+    for &n in nums {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == n => *end = n,
+            _ => ranges.push((n, n)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub struct AbsoluteEpisode {
+    pub num: isize,
+    pub name: Option<String>,
+    pub path: PathBuf,
+}
+
+// Find episodes named with absolute numbering, both in the series directory
+// itself and in any of its season directories
+pub fn absolute_episodes(series_path: &Path) -> Vec<AbsoluteEpisode> {
+    let mut candidates = Vec::new();
+    for p in fs::read_dir(series_path)
+        .expect("Cannot read directory")
+        .map(|e| e.expect("Cannot retreive file information").path())
+    {
+        let name = p.file_name().unwrap().to_str().unwrap();
+        if p.is_dir() && constants::SEASON_RE.is_match(name) {
+            candidates.extend(
+                fs::read_dir(&p)
+                    .expect("Cannot read directory")
+                    .map(|e| e.expect("Cannot retreive file information").path()),
+            );
+        } else {
+            candidates.push(p);
         }
     }
-    */
+    let mut episodes: Vec<_> = candidates
+        .into_iter()
+        .filter(|p| path::is_media_file(p))
+        .filter_map(|p| {
+            let caps = constants::ABS_EP_RE.captures(p.file_name()?.to_str()?)?;
+            Some(AbsoluteEpisode {
+                num: caps["absnum"].parse::<isize>().unwrap(),
+                name: caps.name("epname").map(|m| m.as_str().to_string()),
+                path: p.clone(),
+            })
+        })
+        .collect();
+    episodes.sort_by_key(|e| e.num);
+    episodes
 }
@@ -0,0 +1,244 @@
+// This file provides extraction of the text subtitle tracks embedded in
+// Matroska files into sidecar files, named as Plex expects them to be, for
+// clients which will not show embedded subtitles
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use colored::*;
+
+use super::ass;
+use super::cue::{Cue, SubtitleFormat};
+use super::dir;
+use super::langdetect;
+use super::locale;
+use super::mkv::{self, Block, Track};
+use super::path;
+use super::srt;
+use super::subtitles::SubtitleName;
+
+// Codec IDs of the text subtitle tracks we can extract
+const SRT_CODEC: &str = "S_TEXT/UTF8";
+const ASS_CODEC: &str = "S_TEXT/ASS";
+const SSA_CODEC: &str = "S_TEXT/SSA";
+
+// How long to show a subtitle whose block does not say
+const DEFAULT_DURATION_MS: i64 = 2000;
+
+// Extract the text subtitle tracks of Matroska files to sidecar files, named
+// after the file with each track's language
+pub fn extract_subtitles(paths: &[PathBuf]) {
+    for file in find_matroska_files(paths) {
+        println!("{}", file.display().to_string().bold());
+        let (tracks, blocks) = match mkv::demux(&file, |t| extractable(&t.codec)) {
+            Ok(demuxed) => demuxed,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", file, e);
+                continue;
+            }
+        };
+        let subtitle_tracks: Vec<&Track> = tracks
+            .iter()
+            .filter(|t| t.kind == mkv::TRACK_TYPE_SUBTITLE)
+            .collect();
+        let text_tracks: Vec<&Track> = subtitle_tracks
+            .iter()
+            .copied()
+            .filter(|t| extractable(&t.codec))
+            .collect();
+        if text_tracks.is_empty() {
+            println!("\t{}", "No text subtitle tracks".italic());
+        }
+        for track in text_tracks {
+            extract_track(&file, track, &blocks);
+        }
+        let others = subtitle_tracks
+            .iter()
+            .filter(|t| !extractable(&t.codec))
+            .count();
+        if others > 0 {
+            println!(
+                "\t{}",
+                format!(
+                    "{} image-based or unsupported subtitle tracks cannot be extracted",
+                    others
+                )
+                .yellow()
+            );
+        }
+    }
+}
+
+fn extractable(codec: &str) -> bool {
+    [SRT_CODEC, ASS_CODEC, SSA_CODEC].contains(&codec)
+}
+
+// Write a single track to a sidecar file, reporting where it went
+fn extract_track(file: &Path, track: &Track, blocks: &[Block]) {
+    let mut blocks: Vec<&Block> = blocks.iter().filter(|b| b.track == track.number).collect();
+    blocks.sort_by_key(|b| b.start);
+    let (format, contents) = match track.codec.as_str() {
+        SRT_CODEC => (SubtitleFormat::Srt, write_srt(track, &blocks)),
+        ASS_CODEC => (SubtitleFormat::Ass, write_ass(track, &blocks, false)),
+        _ => (SubtitleFormat::Ssa, write_ass(track, &blocks, true)),
+    };
+    let description = match &track.name {
+        Some(name) => format!("Track {} ({}, \"{}\")", track.number, format, name),
+        None => format!("Track {} ({})", track.number, format),
+    };
+    let (cues, _) = format.parse(&contents);
+    // Fall back to the language of the text when the track does not say
+    let locale = match track_locale(&track.language)
+        .or_else(|| langdetect::detect_cues(&cues).map(String::from))
+    {
+        Some(locale) => locale,
+        None => {
+            eprintln!(
+                "[WARN] {} of {:?} has no language, and none could be detected; not extracted",
+                description, file
+            );
+            return;
+        }
+    };
+    let sub_name = SubtitleName {
+        fname: file.file_stem().unwrap().to_string_lossy().to_string(),
+        locale,
+        forced: track.forced,
+        sdh: track.hearing_impaired,
+        cc: false,
+    };
+    let output = file.with_file_name(sub_name.file_name(format.extension()));
+    if output.exists() {
+        eprintln!(
+            "[ERROR] Cannot extract {} of {:?}: {:?} already exists",
+            description, file, output
+        );
+        return;
+    }
+    if let Err(e) = fs::write(&output, contents) {
+        eprintln!("[ERROR] Cannot write {:?}: {}", output, e);
+        return;
+    }
+    println!(
+        "\t{} [{}]{}{}",
+        description,
+        sub_name.describe(),
+        format!(": {} cues written to ", cues.len()).italic(),
+        output.file_name().unwrap().to_string_lossy().green()
+    );
+}
+
+// The locale to name a track's subtitles with (e.g., "en" or "en-GB"), from
+// its ISO 639-2 code or BCP 47 tag.  None if the language is undetermined
+fn track_locale(language: &str) -> Option<String> {
+    let mut parts = language.split('-');
+    let lang = parts.next()?;
+    // Undetermined, multiple, uncoded, and no linguistic content
+    if ["und", "mul", "mis", "zxx"].contains(&lang.to_lowercase().as_str()) {
+        return None;
+    }
+    let lang = locale::find_language(lang)?;
+    let code = locale::preferred_code(lang);
+    match parts.find(|p| p.len() == 2 && locale::is_region(&p.to_uppercase())) {
+        Some(region) => Some(format!("{}-{}", code, region.to_uppercase())),
+        None => Some(code.to_string()),
+    }
+}
+
+// The end of a block, from its duration, the track's default duration, or
+// else a fixed duration
+fn block_end(track: &Track, block: &Block) -> i64 {
+    let duration = block
+        .duration
+        .or_else(|| track.default_duration.map(|ns| (ns / 1_000_000) as i64))
+        .unwrap_or(DEFAULT_DURATION_MS);
+    block.start + duration
+}
+
+// SRT blocks hold the text of each cue, without numbers or times
+fn write_srt(track: &Track, blocks: &[&Block]) -> String {
+    let cues: Vec<Cue> = blocks
+        .iter()
+        .map(|block| Cue {
+            start: block.start,
+            end: block_end(track, block),
+            line: 0,
+            text: String::from_utf8_lossy(&block.data)
+                .lines()
+                .map(String::from)
+                .collect(),
+            settings: String::new(),
+            style: String::new(),
+        })
+        .collect();
+    srt::write(&cues)
+}
+
+// ASS and SSA tracks keep the file's header (script info and styles) in their
+// codec private data.  Each block holds the fields of a Dialogue line, without
+// its times and starting with its position in the original file:
+// "ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text"
+// (with "Marked" in place of "Layer" for SSA)
+fn write_ass(track: &Track, blocks: &[&Block], ssa: bool) -> String {
+    let mut out = String::from_utf8_lossy(&track.codec_private)
+        .trim_end()
+        .to_string();
+    out.push('\n');
+    if !out.to_lowercase().contains("[events]") {
+        out.push_str("\n[Events]\n");
+        out.push_str(if ssa {
+            "Format: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n"
+        } else {
+            "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n"
+        });
+    }
+    let mut events: Vec<(u64, String)> = blocks
+        .iter()
+        .filter_map(|block| {
+            let data = String::from_utf8_lossy(&block.data);
+            let mut fields = data.splitn(3, ',');
+            let read_order = fields.next()?.trim().parse().unwrap_or(u64::MAX);
+            let first = fields.next()?;
+            let rest = fields.next()?;
+            Some((
+                read_order,
+                format!(
+                    "Dialogue: {},{},{},{}\n",
+                    first,
+                    ass::format_timestamp(block.start),
+                    ass::format_timestamp(block_end(track, block)),
+                    rest.trim_end_matches(['\r', '\n'])
+                ),
+            ))
+        })
+        .collect();
+    // Events are stored in order of time, but written in their original order
+    events.sort_by_key(|(read_order, _)| *read_order);
+    for (_, event) in events {
+        out.push_str(&event);
+    }
+    out
+}
+
+// Matroska files given on the command line, where directories are searched
+// recursively
+fn find_matroska_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for p in paths {
+        if p.is_dir() {
+            files.extend(dir::list_files(p).unwrap_or_default());
+        } else if p.exists() {
+            files.push(p.clone());
+        } else {
+            eprintln!("No such file or directory: {:?}", p);
+            process::exit(1);
+        }
+    }
+    files
+        .into_iter()
+        .filter(|p| {
+            path::get_extension_from_filename(p)
+                .is_some_and(|ext| ["mkv", "webm"].contains(&ext.to_lowercase().as_str()))
+        })
+        .collect()
+}
@@ -0,0 +1,273 @@
+// This file provides checks that media files are what their extension says
+// they are (from the magic bytes at the start of the file), and that they are
+// not empty or obviously truncated
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use colored::*;
+
+use super::dir::{self, MediaType};
+use super::ebml::Reader;
+use super::mkv;
+use super::mp4;
+use super::path;
+
+// Media files smaller than this are almost certainly not a whole film or
+// episode
+const MIN_MEDIA_SIZE: u64 = 1024 * 1024;
+
+// How much of the start of a file to read to tell what it is
+const SNIFF_SIZE: usize = 512;
+
+// Container formats, as told by their magic bytes
+#[derive(PartialEq, Clone, Copy)]
+enum Container {
+    Matroska,
+    WebM,
+    Mp4,
+    Avi,
+    MpegPs,
+    MpegTs,
+    Ogg,
+    Asf,
+    Flv,
+    Swf,
+    // Not a media file at all, but a web page (e.g., an error page saved in
+    // place of a download)
+    Html,
+}
+
+impl Container {
+    fn name(&self) -> &'static str {
+        match self {
+            Container::Matroska => "Matroska",
+            Container::WebM => "WebM",
+            Container::Mp4 => "MP4/QuickTime",
+            Container::Avi => "AVI",
+            Container::MpegPs => "MPEG program stream",
+            Container::MpegTs => "MPEG transport stream",
+            Container::Ogg => "Ogg",
+            Container::Asf => "ASF (Windows Media)",
+            Container::Flv => "Flash video",
+            Container::Swf => "Flash",
+            Container::Html => "HTML",
+        }
+    }
+
+    // Extensions from MEDIA_TYPES a file in this container may have.  The
+    // first is the one we would suggest
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            // WebM is a subset of Matroska, so may be named either way
+            Container::Matroska => &["mkv"],
+            Container::WebM => &["webm", "mkv"],
+            Container::Mp4 => &["mp4", "m4v", "m4p", "mov", "qt"],
+            Container::Avi => &["avi"],
+            Container::MpegPs => &["mpg", "mpeg", "mpe", "mpv", "mp2"],
+            Container::MpegTs => &["avdchd", "mpg", "mpeg"],
+            Container::Ogg => &["ogg"],
+            Container::Asf => &["wmv"],
+            Container::Flv => &["flv"],
+            Container::Swf => &["swf"],
+            Container::Html => &[],
+        }
+    }
+}
+
+// Tell which container a file is in from the start of it
+fn sniff(head: &[u8]) -> Option<Container> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        // The document type is in the EBML header, near the start
+        let webm = head.windows(4).any(|w| w == b"webm");
+        return Some(if webm {
+            Container::WebM
+        } else {
+            Container::Matroska
+        });
+    }
+    // Box types which may start an MP4 or QuickTime file
+    if [
+        b"ftyp", b"moov", b"mdat", b"free", b"wide", b"skip", b"pnot",
+    ]
+    .iter()
+    .any(|kind| at(4, *kind))
+    {
+        return Some(Container::Mp4);
+    }
+    if at(0, b"RIFF") && at(8, b"AVI ") {
+        return Some(Container::Avi);
+    }
+    if at(0, &[0x00, 0x00, 0x01, 0xBA]) {
+        return Some(Container::MpegPs);
+    }
+    // Transport stream packets are 188 bytes, each starting with a sync byte
+    // (or 192 bytes, with a timestamp first, as in M2TS)
+    if (at(0, &[0x47]) && at(188, &[0x47])) || (at(4, &[0x47]) && at(196, &[0x47])) {
+        return Some(Container::MpegTs);
+    }
+    if at(0, b"OggS") {
+        return Some(Container::Ogg);
+    }
+    if at(0, &[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11]) {
+        return Some(Container::Asf);
+    }
+    if at(0, b"FLV") {
+        return Some(Container::Flv);
+    }
+    if at(0, b"FWS") || at(0, b"CWS") || at(0, b"ZWS") {
+        return Some(Container::Swf);
+    }
+    let text = String::from_utf8_lossy(head).trim_start().to_lowercase();
+    if text.starts_with("<!doctype html") || text.starts_with("<html") {
+        return Some(Container::Html);
+    }
+    None
+}
+
+// Check every media file in each library, reporting those with problems
+pub fn check_media_integrity(dirname: &Path, media_type: &MediaType) {
+    for library in media_type.libraries() {
+        let mut library_dir = dirname.to_path_buf();
+        library_dir.push(library.as_str());
+        let files: Vec<_> = dir::list_files(&library_dir)
+            .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", library_dir))
+            .into_iter()
+            .filter(|p| path::is_media_file(p))
+            .collect();
+
+        println!("{}", library.as_str().blue().bold());
+        let mut with_problems = 0;
+        for file in &files {
+            let problems = match check_file(file) {
+                Ok(problems) => problems,
+                Err(e) => vec![format!("Cannot read file: {}", e)],
+            };
+            if problems.is_empty() {
+                continue;
+            }
+            with_problems += 1;
+            let rel = file.strip_prefix(&library_dir).unwrap_or(file);
+            println!("\t{}", rel.display().to_string().bold());
+            for problem in problems {
+                println!("\t\t- {}", problem.italic());
+            }
+        }
+        if with_problems == 0 {
+            println!(
+                "\t{}",
+                format!("{} media files checked; no problems found", files.len()).italic()
+            );
+        } else {
+            println!(
+                "\t{}{}{}",
+                with_problems.to_string().bold(),
+                " of ".italic(),
+                format!("{} media files have problems", files.len()).italic()
+            );
+        }
+    }
+}
+
+// The problems with a single media file
+fn check_file(p: &Path) -> io::Result<Vec<String>> {
+    let mut file = File::open(p)?;
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(vec!["Empty file".to_string()]);
+    }
+    let mut problems = Vec::new();
+    if len < MIN_MEDIA_SIZE {
+        problems.push(format!(
+            "Suspiciously small ({} bytes); it may be incomplete",
+            len
+        ));
+    }
+
+    let mut head = Vec::with_capacity(SNIFF_SIZE);
+    (&mut file).take(SNIFF_SIZE as u64).read_to_end(&mut head)?;
+    let container = match sniff(&head) {
+        Some(container) => container,
+        None => {
+            problems.push("Contents are not a media container we recognise".to_string());
+            return Ok(problems);
+        }
+    };
+    let ext = path::get_extension_from_filename(p)
+        .unwrap_or_default()
+        .to_lowercase();
+    if container == Container::Html {
+        problems.push("Contents are a web page, not media".to_string());
+        return Ok(problems);
+    }
+    if !container.extensions().contains(&ext.as_str()) {
+        problems.push(format!(
+            "Contents are {}, not what the .{} extension suggests; it should be .{}",
+            container.name(),
+            ext,
+            container.extensions()[0]
+        ));
+    }
+    problems.extend(check_truncation(p, container, &head, len)?);
+    Ok(problems)
+}
+
+// Whether a container says it is longer than the file is
+fn check_truncation(
+    p: &Path,
+    container: Container,
+    head: &[u8],
+    len: u64,
+) -> io::Result<Option<String>> {
+    // The file ends part-way through the headers we read
+    let cut_short = Some(format!(
+        "Truncated: the file ends part-way through the {} headers",
+        container.name()
+    ));
+    let end = match container {
+        Container::Matroska | Container::WebM => {
+            let mut reader = Reader::new(File::open(p)?)?;
+            match mkv::read_segment_header(&mut reader) {
+                // Segments written while streaming may have an unknown size
+                Ok((_, segment)) => segment.end(),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(cut_short),
+                Err(e) => return Ok(Some(format!("Cannot read Matroska header: {}", e))),
+            }
+        }
+        Container::Mp4 => {
+            let headers = match mp4::read_top_level(&mut File::open(p)?) {
+                Ok(headers) => headers,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(cut_short),
+                Err(e) => return Err(e),
+            };
+            if !headers.iter().any(|h| &h.kind == b"moov") {
+                return Ok(Some(
+                    "No movie (moov) box; the file is incomplete".to_string(),
+                ));
+            }
+            match headers.last().map(|h| h.end()) {
+                Some(None) => {
+                    return Ok(Some(format!(
+                        "Truncated: the {} container says it is larger than any file can be",
+                        container.name()
+                    )))
+                }
+                end => end.flatten(),
+            }
+        }
+        // The RIFF size does not include the eight bytes before it
+        Container::Avi => head
+            .get(4..8)
+            .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as u64 + 8),
+        _ => None,
+    };
+    Ok(end.filter(|end| *end > len).map(|end| {
+        format!(
+            "Truncated: the {} container is {} bytes, but the file is only {}",
+            container.name(),
+            end,
+            len
+        )
+    }))
+}
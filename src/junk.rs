@@ -0,0 +1,148 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use super::dir::{self, MediaType};
+use super::path;
+
+pub const QUARANTINE_DIR_NAME: &str = ".quarantine";
+
+// Kinds of file which do not belong in the library
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum JunkCategory {
+    // Files created by the operating system (e.g., .DS_Store, Thumbs.db)
+    OsMetadata,
+    // Downloads which never finished (e.g., *.part, *.crdownload)
+    PartialDownload,
+    // Sample clips which come with some releases
+    Sample,
+}
+
+impl fmt::Display for JunkCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            JunkCategory::OsMetadata => "OS metadata",
+            JunkCategory::PartialDownload => "partial download",
+            JunkCategory::Sample => "sample",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+const OS_METADATA_FILES: [&str; 4] = [".ds_store", "thumbs.db", "desktop.ini", "ehthumbs.db"];
+const PARTIAL_DOWNLOAD_TYPES: [&str; 5] = ["part", "!qb", "crdownload", "partial", "download"];
+
+pub fn junk_category(p: &Path) -> Option<JunkCategory> {
+    let name = p.file_name()?.to_str()?;
+    let lower = name.to_lowercase();
+    // AppleDouble files (._*) hold resource forks from macOS
+    if OS_METADATA_FILES.contains(&lower.as_str()) || name.starts_with("._") {
+        return Some(JunkCategory::OsMetadata);
+    }
+    let ext = path::get_extension_from_filename(p).map(str::to_lowercase);
+    if ext.is_some_and(|ext| PARTIAL_DOWNLOAD_TYPES.contains(&ext.as_str())) {
+        return Some(JunkCategory::PartialDownload);
+    }
+    let stem = p.file_stem()?.to_str()?.to_lowercase();
+    if path::is_media_file(p)
+        && (stem == "sample" || stem.ends_with("-sample") || stem.ends_with(".sample"))
+    {
+        return Some(JunkCategory::Sample);
+    }
+    None
+}
+
+fn list_junk(dirname: &Path, media_type: &MediaType) -> Vec<(PathBuf, JunkCategory)> {
+    let mut junk = Vec::new();
+    for library in media_type.libraries() {
+        let mut library_dir = dirname.to_path_buf();
+        library_dir.push(library.as_str());
+        let files = dir::list_files(&library_dir)
+            .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", library_dir));
+        junk.extend(
+            files
+                .into_iter()
+                .filter_map(|p| junk_category(&p).map(|c| (p, c))),
+        );
+    }
+    junk
+}
+
+pub fn list_junk_files(dirname: &Path, media_type: &MediaType) {
+    for (p, category) in list_junk(dirname, media_type) {
+        let rel = p.strip_prefix(dirname).unwrap_or(&p);
+        println!(
+            "{} {}",
+            format!("[{}]", category).yellow(),
+            rel.display().to_string().bold()
+        );
+    }
+}
+
+// Move junk files into a quarantine directory, keeping their path relative to
+// the media directory so that they can be restored if need be
+pub fn clean_junk_files(
+    dirname: &Path,
+    media_type: &MediaType,
+    quarantine: Option<&Path>,
+    dry_run: bool,
+) {
+    let quarantine = match quarantine {
+        Some(quarantine) => quarantine.to_path_buf(),
+        None => {
+            let mut quarantine = dirname.to_path_buf();
+            quarantine.push(QUARANTINE_DIR_NAME);
+            quarantine
+        }
+    };
+    let junk = list_junk(dirname, media_type);
+    if junk.is_empty() {
+        println!("{}", "No junk files found".italic());
+        return;
+    }
+    let mut moved = 0;
+    for (p, category) in &junk {
+        let rel = p.strip_prefix(dirname).unwrap_or(p);
+        let dest = quarantine.join(rel);
+        println!(
+            "{}{} {} -> {}",
+            if dry_run { "[DRY RUN] " } else { "" },
+            format!("[{}]", category).yellow(),
+            rel.display().to_string().bold(),
+            dest.display()
+        );
+        if dry_run {
+            continue;
+        }
+        if dest.exists() {
+            eprintln!("[ERROR] Not overwriting existing file {:?}", dest);
+            continue;
+        }
+        let result = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::rename(p, &dest));
+        match result {
+            Ok(()) => moved += 1,
+            Err(e) => eprintln!("[ERROR] Cannot move {:?} to {:?}: {}", p, dest, e),
+        }
+    }
+    if dry_run {
+        println!(
+            "{}{}{}",
+            "Would move ".italic(),
+            junk.len().to_string().bold(),
+            " files to quarantine".italic()
+        );
+    } else {
+        println!(
+            "{}{}{}{}",
+            "Moved ".italic(),
+            moved.to_string().bold(),
+            " files to ".italic(),
+            quarantine.display()
+        );
+    }
+}
@@ -0,0 +1,658 @@
+// This file provides offline detection of the language of subtitle text.
+//
+// Languages with their own script (e.g., Greek, Japanese) are told apart by
+// the characters used.  Otherwise, we count how many words are common words
+// (stopwords) of each language.  Closely related languages share many of
+// these, so the winner must also have more of its own words than any close
+// rival has of theirs
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::cue::{Cue, SubtitleFormat};
+use super::encoding;
+use super::locale;
+use super::subtitles::{self, SubtitleName};
+
+lazy_static! {
+    // Markup in cue text: HTML-like tags, and ASS override blocks
+    static ref MARKUP_RE: Regex = Regex::new(r"<[^>]*>|\{[^}]*\}|\\[Nnh]").unwrap();
+}
+
+// Words which make up much of everyday speech in each language, by ISO 639-1
+// code
+const STOPWORDS: [(&str, &[&str]); 19] = [
+    (
+        "en",
+        &[
+            "the", "and", "you", "that", "to", "of", "is", "it", "what", "this", "in", "i'm",
+            "don't", "have", "was", "for", "with", "are", "not", "be", "your", "we", "he", "she",
+            "they", "just", "know", "can", "me", "my", "all", "there", "but", "so", "do", "it's",
+            "if", "about", "will", "would", "right", "here", "how", "yeah", "that's", "get", "got",
+            "want", "going", "were", "been",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "que", "de", "y", "es", "en", "un", "una", "no", "por",
+            "para", "con", "qué", "está", "pero", "lo", "se", "mi", "tu", "yo", "eso", "esto",
+            "muy", "bien", "sí", "cómo", "aquí", "ahora", "tengo", "tiene", "hay", "nada", "todo",
+            "estoy", "vamos", "puedo", "porque", "también", "él", "ella", "usted", "del", "al",
+            "ya", "sé",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "de", "des", "et", "est", "je", "tu", "il", "elle", "nous", "vous",
+            "un", "une", "pas", "que", "qui", "ce", "c'est", "ne", "en", "dans", "pour", "avec",
+            "sur", "mais", "oui", "non", "mon", "ton", "son", "moi", "toi", "bien", "tout", "j'ai",
+            "suis", "très", "ça", "au", "aux", "du", "sont", "était", "fait", "là", "où",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "ich", "du", "nicht", "ein", "eine", "zu", "sie",
+            "es", "wir", "ihr", "mit", "den", "dem", "auf", "für", "von", "was", "wie", "aber",
+            "ja", "nein", "hier", "mein", "dein", "auch", "noch", "nur", "bin", "hast", "habe",
+            "sind", "war", "kann", "muss", "schon", "doch", "mal", "jetzt", "nichts", "wird",
+            "dass", "gut", "sehr", "mich", "dich", "uns",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "lo", "la", "gli", "le", "di", "che", "è", "e", "un", "una", "non", "per", "con",
+            "sono", "sei", "mi", "ti", "ci", "si", "ma", "cosa", "come", "qui", "questo", "quello",
+            "bene", "anche", "ho", "hai", "ha", "della", "del", "nel", "molto", "io", "tu", "lui",
+            "lei", "noi", "voi", "perché", "sì", "grazie", "allora", "adesso", "dove", "niente",
+            "tutto", "ancora",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "a", "os", "as", "de", "que", "e", "é", "um", "uma", "não", "para", "com", "do",
+            "da", "em", "no", "na", "eu", "você", "ele", "ela", "nós", "mas", "isso", "isto",
+            "muito", "bem", "sim", "aqui", "agora", "tenho", "tem", "está", "estou", "por", "como",
+            "onde", "nada", "tudo", "meu", "minha", "seu", "sua", "obrigado", "então", "vamos",
+            "também", "já",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "van", "ik", "je", "is", "dat", "niet", "wat", "zijn", "op",
+            "te", "in", "met", "voor", "hij", "ze", "we", "maar", "er", "dit", "die", "hebben",
+            "heb", "heeft", "was", "ben", "bent", "kan", "moet", "nog", "wel", "ook", "hier",
+            "daar", "naar", "mijn", "jouw", "jij", "zo", "goed", "nee", "ja", "alles", "niets",
+            "waarom", "hoe", "gaan",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "att", "det", "som", "en", "är", "på", "för", "med", "jag", "du", "han", "hon",
+            "vi", "ni", "de", "inte", "har", "var", "den", "till", "av", "om", "så", "men", "vad",
+            "här", "där", "kan", "ska", "vill", "mig", "dig", "oss", "nu", "bara", "också", "ja",
+            "nej", "min", "din", "hur", "varför", "något", "ingen", "allt",
+        ],
+    ),
+    (
+        "da",
+        &[
+            "og", "at", "det", "er", "en", "til", "på", "med", "jeg", "du", "han", "hun", "vi",
+            "i", "de", "ikke", "har", "var", "den", "af", "om", "så", "men", "hvad", "her", "der",
+            "kan", "skal", "vil", "mig", "dig", "os", "nu", "bare", "også", "ja", "nej", "min",
+            "din", "hvordan", "hvorfor", "noget", "ingen", "alt", "være", "et",
+        ],
+    ),
+    (
+        "no",
+        &[
+            "og", "at", "det", "er", "en", "til", "på", "med", "jeg", "du", "han", "hun", "vi",
+            "dere", "de", "ikke", "har", "var", "den", "av", "om", "så", "men", "hva", "her",
+            "der", "kan", "skal", "vil", "meg", "deg", "oss", "nå", "bare", "også", "ja", "nei",
+            "min", "din", "hvordan", "hvorfor", "noe", "ingen", "alt", "være", "et",
+        ],
+    ),
+    (
+        "fi",
+        &[
+            "ja",
+            "on",
+            "ei",
+            "se",
+            "että",
+            "en",
+            "et",
+            "hän",
+            "me",
+            "te",
+            "he",
+            "minä",
+            "sinä",
+            "mitä",
+            "mikä",
+            "tämä",
+            "tuo",
+            "ole",
+            "olen",
+            "olet",
+            "oli",
+            "kun",
+            "mutta",
+            "niin",
+            "nyt",
+            "jo",
+            "vain",
+            "myös",
+            "kanssa",
+            "siis",
+            "kiitos",
+            "hyvä",
+            "missä",
+            "miksi",
+            "miten",
+            "kaikki",
+            "mitään",
+            "minun",
+            "sinun",
+            "tässä",
+            "siellä",
+            "täällä",
+        ],
+    ),
+    (
+        "pl",
+        &[
+            "i",
+            "w",
+            "z",
+            "na",
+            "się",
+            "nie",
+            "to",
+            "jest",
+            "że",
+            "do",
+            "co",
+            "jak",
+            "ale",
+            "ja",
+            "ty",
+            "on",
+            "ona",
+            "my",
+            "wy",
+            "oni",
+            "mnie",
+            "cię",
+            "mi",
+            "ci",
+            "tak",
+            "już",
+            "tu",
+            "tym",
+            "tego",
+            "być",
+            "jestem",
+            "jesteś",
+            "mam",
+            "masz",
+            "był",
+            "była",
+            "dobrze",
+            "wszystko",
+            "nic",
+            "dlaczego",
+            "gdzie",
+            "kiedy",
+            "proszę",
+            "dziękuję",
+            "może",
+            "jeszcze",
+            "tylko",
+        ],
+    ),
+    (
+        "cs",
+        &[
+            "a", "je", "to", "se", "na", "v", "že", "s", "z", "do", "ne", "co", "jak", "ale", "já",
+            "ty", "on", "ona", "my", "vy", "oni", "mě", "tě", "mi", "ti", "tak", "už", "tady",
+            "tam", "jsem", "jsi", "jsou", "byl", "byla", "mám", "máš", "dobře", "všechno", "nic",
+            "proč", "kde", "kdy", "prosím", "děkuji", "může", "ještě", "jen", "není",
+        ],
+    ),
+    (
+        "ro",
+        &[
+            "și",
+            "în",
+            "de",
+            "la",
+            "nu",
+            "că",
+            "pe",
+            "cu",
+            "este",
+            "e",
+            "o",
+            "un",
+            "ce",
+            "sunt",
+            "eu",
+            "tu",
+            "el",
+            "ea",
+            "noi",
+            "voi",
+            "ei",
+            "mai",
+            "dar",
+            "asta",
+            "acest",
+            "aici",
+            "acum",
+            "bine",
+            "da",
+            "foarte",
+            "pentru",
+            "din",
+            "ai",
+            "am",
+            "are",
+            "fost",
+            "când",
+            "unde",
+            "cum",
+            "tot",
+            "nimic",
+            "mulțumesc",
+            "vreau",
+            "poate",
+        ],
+    ),
+    (
+        "tr",
+        &[
+            "ve", "bir", "bu", "da", "de", "ne", "için", "ben", "sen", "o", "biz", "siz", "onlar",
+            "değil", "var", "yok", "mi", "mı", "mu", "çok", "ama", "evet", "hayır", "şey", "gibi",
+            "daha", "şimdi", "burada", "nasıl", "neden", "tamam", "seni", "beni", "benim", "senin",
+            "bana", "sana", "her", "kadar", "olarak",
+        ],
+    ),
+    (
+        "hu",
+        &[
+            "a",
+            "az",
+            "és",
+            "hogy",
+            "nem",
+            "is",
+            "egy",
+            "ez",
+            "de",
+            "meg",
+            "van",
+            "volt",
+            "csak",
+            "már",
+            "még",
+            "mi",
+            "te",
+            "ő",
+            "én",
+            "mit",
+            "miért",
+            "hol",
+            "hogyan",
+            "igen",
+            "jó",
+            "itt",
+            "ott",
+            "most",
+            "minden",
+            "semmi",
+            "köszönöm",
+            "vagy",
+            "lesz",
+            "kell",
+            "nagyon",
+            "neked",
+            "nekem",
+        ],
+    ),
+    (
+        "ru",
+        &[
+            "и",
+            "в",
+            "не",
+            "на",
+            "я",
+            "что",
+            "ты",
+            "он",
+            "она",
+            "мы",
+            "вы",
+            "они",
+            "это",
+            "как",
+            "но",
+            "да",
+            "нет",
+            "так",
+            "все",
+            "его",
+            "её",
+            "меня",
+            "тебя",
+            "мне",
+            "тебе",
+            "был",
+            "была",
+            "есть",
+            "здесь",
+            "там",
+            "сейчас",
+            "почему",
+            "где",
+            "когда",
+            "хорошо",
+            "ничего",
+            "спасибо",
+            "пожалуйста",
+            "можно",
+            "надо",
+            "уже",
+            "ещё",
+            "только",
+        ],
+    ),
+    (
+        "uk",
+        &[
+            "і",
+            "в",
+            "не",
+            "на",
+            "я",
+            "що",
+            "ти",
+            "він",
+            "вона",
+            "ми",
+            "ви",
+            "вони",
+            "це",
+            "як",
+            "але",
+            "так",
+            "ні",
+            "все",
+            "його",
+            "її",
+            "мене",
+            "тебе",
+            "мені",
+            "тобі",
+            "був",
+            "була",
+            "є",
+            "тут",
+            "там",
+            "зараз",
+            "чому",
+            "де",
+            "коли",
+            "добре",
+            "нічого",
+            "дякую",
+            "будь",
+            "можна",
+            "треба",
+            "вже",
+            "ще",
+            "тільки",
+            "й",
+        ],
+    ),
+    (
+        "bg",
+        &[
+            "и",
+            "в",
+            "не",
+            "на",
+            "аз",
+            "че",
+            "ти",
+            "той",
+            "тя",
+            "ние",
+            "вие",
+            "те",
+            "това",
+            "как",
+            "но",
+            "да",
+            "така",
+            "всичко",
+            "го",
+            "мен",
+            "теб",
+            "ми",
+            "беше",
+            "е",
+            "са",
+            "тук",
+            "там",
+            "сега",
+            "защо",
+            "къде",
+            "кога",
+            "добре",
+            "нищо",
+            "благодаря",
+            "моля",
+            "може",
+            "трябва",
+            "вече",
+            "още",
+            "само",
+            "ще",
+        ],
+    ),
+];
+
+// Languages identified by their script alone, by the range of characters
+const SCRIPTS: [(&str, char, char); 8] = [
+    ("el", '\u{0370}', '\u{03FF}'),
+    ("he", '\u{0590}', '\u{05FF}'),
+    ("ar", '\u{0600}', '\u{06FF}'),
+    ("hi", '\u{0900}', '\u{097F}'),
+    ("th", '\u{0E00}', '\u{0E7F}'),
+    ("ja", '\u{3040}', '\u{30FF}'),
+    ("ko", '\u{AC00}', '\u{D7AF}'),
+    ("zh", '\u{4E00}', '\u{9FFF}'),
+];
+
+// Below this many stopwords (or letters, for languages with their own
+// script), there is too little text to tell
+const MIN_STOPWORDS: usize = 8;
+const MIN_LETTERS: usize = 10;
+
+// Detect the language of some text, as an ISO 639-1 code
+pub fn detect(text: &str) -> Option<&'static str> {
+    if let Some(detection) = detect_script(text) {
+        return Some(detection);
+    }
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphabetic() || c == '\'' || c == '’'))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase().replace('’', "'"))
+        .collect();
+    let lists: Vec<(&str, HashSet<&str>)> = STOPWORDS
+        .iter()
+        .map(|(code, words)| (*code, words.iter().copied().collect()))
+        .collect();
+    let hits: Vec<usize> = lists
+        .iter()
+        .map(|(_, list)| words.iter().filter(|w| list.contains(w.as_str())).count())
+        .collect();
+    let max_hits = *hits.iter().max()?;
+    if max_hits < MIN_STOPWORDS {
+        return None;
+    }
+    // Only words which one list has and the other lacks can tell two
+    // languages apart
+    let distinct = |a: usize, b: usize| {
+        words
+            .iter()
+            .filter(|w| lists[a].1.contains(w.as_str()) && !lists[b].1.contains(w.as_str()))
+            .count()
+    };
+    let rivals: Vec<usize> = (0..lists.len())
+        .filter(|i| hits[*i] * 10 >= max_hits * 6)
+        .collect();
+    let mut best = hits.iter().position(|h| *h == max_hits)?;
+    for &rival in &rivals {
+        if rival != best && distinct(rival, best) > distinct(best, rival) {
+            best = rival;
+        }
+    }
+    for &rival in &rivals {
+        if rival != best && distinct(best, rival) < (2 * distinct(rival, best)).max(2) {
+            // Too close to call
+            return None;
+        }
+    }
+    Some(lists[best].0)
+}
+
+// Detect languages with their own script.  Latin and Cyrillic are shared by
+// many languages, so are left to the stopwords
+fn detect_script(text: &str) -> Option<&'static str> {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() < MIN_LETTERS {
+        return None;
+    }
+    let counts: Vec<usize> = SCRIPTS
+        .iter()
+        .map(|(_, lo, hi)| letters.iter().filter(|c| (*lo..=*hi).contains(*c)).count())
+        .collect();
+    let share = |code: &str| {
+        let i = SCRIPTS.iter().position(|(c, _, _)| *c == code).unwrap();
+        counts[i] as f64 / letters.len() as f64
+    };
+    // Japanese mixes kana with Chinese characters
+    if share("ja") > 0.05 {
+        return Some("ja");
+    }
+    let (i, _) = counts.iter().enumerate().max_by_key(|(_, c)| **c)?;
+    let code = SCRIPTS[i].0;
+    if share(code) < 0.5 {
+        return None;
+    }
+    Some(code)
+}
+
+// Detect the language of a text subtitle file from its cues
+pub fn detect_subtitle(p: &Path) -> Option<&'static str> {
+    let format = SubtitleFormat::from_path(p)?;
+    let (contents, _) = encoding::read_to_string(p).ok()?;
+    let (cues, _) = format.parse(&contents);
+    detect_cues(&cues)
+}
+
+// Detect the language of parsed cues, ignoring their markup
+pub fn detect_cues(cues: &[Cue]) -> Option<&'static str> {
+    let text: Vec<String> = cues
+        .iter()
+        .flat_map(|c| c.text.iter())
+        .map(|l| MARKUP_RE.replace_all(l, " ").to_string())
+        .collect();
+    detect(&text.join("\n"))
+}
+
+// Whether a detected language is the language a subtitle is tagged with
+fn same_language(detected: &str, tagged: &str) -> bool {
+    let tagged = locale::find_language(tagged)
+        .map(locale::preferred_code)
+        .unwrap_or(tagged);
+    match detected {
+        // Norwegian has two written forms, Bokmål and Nynorsk
+        "no" => ["no", "nb", "nn"].contains(&tagged),
+        _ => detected == tagged,
+    }
+}
+
+// Report subtitle files whose text appears to be in a different language to
+// the one in their name
+pub fn check_subtitle_languages(paths: &[PathBuf]) {
+    let mut checked = 0;
+    let mut undetected = 0;
+    let mut mismatched = 0;
+    for sub in subtitles::find_subtitles(paths) {
+        let name = sub.file_name().unwrap().to_string_lossy().to_string();
+        let sub_name = match SubtitleName::parse(&name) {
+            Some(sub_name) => sub_name,
+            // Badly-named subtitles are reported by -C
+            None => continue,
+        };
+        if SubtitleFormat::from_path(&sub).is_none() {
+            continue;
+        }
+        checked += 1;
+        let detected = match detect_subtitle(&sub) {
+            Some(detected) => detected,
+            None => {
+                undetected += 1;
+                continue;
+            }
+        };
+        if same_language(detected, sub_name.language()) {
+            continue;
+        }
+        mismatched += 1;
+        println!(
+            "{} [{}]{}{}",
+            sub.display().to_string().bold(),
+            sub_name.describe(),
+            " appears to be in ".italic(),
+            locale::language_name(detected).unwrap_or(detected).yellow()
+        );
+        let ext = sub.extension().unwrap().to_string_lossy().to_lowercase();
+        let fixed = SubtitleName {
+            locale: detected.to_string(),
+            ..sub_name
+        };
+        println!(
+            "\t{}{}",
+            "Suggested name: ".italic(),
+            fixed.file_name(&ext).green()
+        );
+    }
+    println!(
+        "{}{}{}{}",
+        mismatched.to_string().bold(),
+        " of ".italic(),
+        checked.to_string().bold(),
+        " subtitle files appear to be in a different language to their name".italic()
+    );
+    if undetected > 0 {
+        println!(
+            "{}{}",
+            undetected.to_string().bold(),
+            " subtitle files had too little text to detect their language".italic()
+        );
+    }
+}
@@ -0,0 +1,332 @@
+// This file provides tables of language and region codes, used to validate
+// the locale of subtitle files (e.g., "en", "eng", or "en-GB").
+//
+// Plex prefers the two-letter ISO 639-1 language codes, so a three-letter
+// ISO 639-2 code is only considered correct when the language has no
+// two-letter code.  Regions follow BCP 47, which uses ISO 3166-1 alpha-2.
+
+pub struct Language {
+    // ISO 639-1 code, if the language has one
+    pub iso1: Option<&'static str>,
+    // ISO 639-2/T (terminology) code
+    pub iso2t: &'static str,
+    // ISO 639-2/B (bibliographic) code, where it differs from ISO 639-2/T
+    pub iso2b: Option<&'static str>,
+    pub name: &'static str,
+}
+
+macro_rules! languages {
+    ($(($iso1:expr, $iso2t:expr, $iso2b:expr, $name:expr)),* $(,)?) => {
+        &[$(Language {
+            iso1: if $iso1.is_empty() { None } else { Some($iso1) },
+            iso2t: $iso2t,
+            iso2b: if $iso2b.is_empty() { None } else { Some($iso2b) },
+            name: $name,
+        }),*]
+    };
+}
+
+// All ISO 639-1 languages, followed by ISO 639-2 languages (without an
+// ISO 639-1 code) which we might reasonably find subtitles for
+const LANGUAGES: &[Language] = languages![
+    ("aa", "aar", "", "Afar"),
+    ("ab", "abk", "", "Abkhazian"),
+    ("ae", "ave", "", "Avestan"),
+    ("af", "afr", "", "Afrikaans"),
+    ("ak", "aka", "", "Akan"),
+    ("am", "amh", "", "Amharic"),
+    ("an", "arg", "", "Aragonese"),
+    ("ar", "ara", "", "Arabic"),
+    ("as", "asm", "", "Assamese"),
+    ("av", "ava", "", "Avaric"),
+    ("ay", "aym", "", "Aymara"),
+    ("az", "aze", "", "Azerbaijani"),
+    ("ba", "bak", "", "Bashkir"),
+    ("be", "bel", "", "Belarusian"),
+    ("bg", "bul", "", "Bulgarian"),
+    ("bi", "bis", "", "Bislama"),
+    ("bm", "bam", "", "Bambara"),
+    ("bn", "ben", "", "Bengali"),
+    ("bo", "bod", "tib", "Tibetan"),
+    ("br", "bre", "", "Breton"),
+    ("bs", "bos", "", "Bosnian"),
+    ("ca", "cat", "", "Catalan"),
+    ("ce", "che", "", "Chechen"),
+    ("ch", "cha", "", "Chamorro"),
+    ("co", "cos", "", "Corsican"),
+    ("cr", "cre", "", "Cree"),
+    ("cs", "ces", "cze", "Czech"),
+    ("cu", "chu", "", "Church Slavic"),
+    ("cv", "chv", "", "Chuvash"),
+    ("cy", "cym", "wel", "Welsh"),
+    ("da", "dan", "", "Danish"),
+    ("de", "deu", "ger", "German"),
+    ("dv", "div", "", "Divehi"),
+    ("dz", "dzo", "", "Dzongkha"),
+    ("ee", "ewe", "", "Ewe"),
+    ("el", "ell", "gre", "Greek"),
+    ("en", "eng", "", "English"),
+    ("eo", "epo", "", "Esperanto"),
+    ("es", "spa", "", "Spanish"),
+    ("et", "est", "", "Estonian"),
+    ("eu", "eus", "baq", "Basque"),
+    ("fa", "fas", "per", "Persian"),
+    ("ff", "ful", "", "Fulah"),
+    ("fi", "fin", "", "Finnish"),
+    ("fj", "fij", "", "Fijian"),
+    ("fo", "fao", "", "Faroese"),
+    ("fr", "fra", "fre", "French"),
+    ("fy", "fry", "", "Western Frisian"),
+    ("ga", "gle", "", "Irish"),
+    ("gd", "gla", "", "Scottish Gaelic"),
+    ("gl", "glg", "", "Galician"),
+    ("gn", "grn", "", "Guarani"),
+    ("gu", "guj", "", "Gujarati"),
+    ("gv", "glv", "", "Manx"),
+    ("ha", "hau", "", "Hausa"),
+    ("he", "heb", "", "Hebrew"),
+    ("hi", "hin", "", "Hindi"),
+    ("ho", "hmo", "", "Hiri Motu"),
+    ("hr", "hrv", "", "Croatian"),
+    ("ht", "hat", "", "Haitian Creole"),
+    ("hu", "hun", "", "Hungarian"),
+    ("hy", "hye", "arm", "Armenian"),
+    ("hz", "her", "", "Herero"),
+    ("ia", "ina", "", "Interlingua"),
+    ("id", "ind", "", "Indonesian"),
+    ("ie", "ile", "", "Interlingue"),
+    ("ig", "ibo", "", "Igbo"),
+    ("ii", "iii", "", "Sichuan Yi"),
+    ("ik", "ipk", "", "Inupiaq"),
+    ("io", "ido", "", "Ido"),
+    ("is", "isl", "ice", "Icelandic"),
+    ("it", "ita", "", "Italian"),
+    ("iu", "iku", "", "Inuktitut"),
+    ("ja", "jpn", "", "Japanese"),
+    ("jv", "jav", "", "Javanese"),
+    ("ka", "kat", "geo", "Georgian"),
+    ("kg", "kon", "", "Kongo"),
+    ("ki", "kik", "", "Kikuyu"),
+    ("kj", "kua", "", "Kuanyama"),
+    ("kk", "kaz", "", "Kazakh"),
+    ("kl", "kal", "", "Kalaallisut"),
+    ("km", "khm", "", "Khmer"),
+    ("kn", "kan", "", "Kannada"),
+    ("ko", "kor", "", "Korean"),
+    ("kr", "kau", "", "Kanuri"),
+    ("ks", "kas", "", "Kashmiri"),
+    ("ku", "kur", "", "Kurdish"),
+    ("kv", "kom", "", "Komi"),
+    ("kw", "cor", "", "Cornish"),
+    ("ky", "kir", "", "Kyrgyz"),
+    ("la", "lat", "", "Latin"),
+    ("lb", "ltz", "", "Luxembourgish"),
+    ("lg", "lug", "", "Ganda"),
+    ("li", "lim", "", "Limburgish"),
+    ("ln", "lin", "", "Lingala"),
+    ("lo", "lao", "", "Lao"),
+    ("lt", "lit", "", "Lithuanian"),
+    ("lu", "lub", "", "Luba-Katanga"),
+    ("lv", "lav", "", "Latvian"),
+    ("mg", "mlg", "", "Malagasy"),
+    ("mh", "mah", "", "Marshallese"),
+    ("mi", "mri", "mao", "Maori"),
+    ("mk", "mkd", "mac", "Macedonian"),
+    ("ml", "mal", "", "Malayalam"),
+    ("mn", "mon", "", "Mongolian"),
+    ("mr", "mar", "", "Marathi"),
+    ("ms", "msa", "may", "Malay"),
+    ("mt", "mlt", "", "Maltese"),
+    ("my", "mya", "bur", "Burmese"),
+    ("na", "nau", "", "Nauru"),
+    ("nb", "nob", "", "Norwegian Bokmål"),
+    ("nd", "nde", "", "North Ndebele"),
+    ("ne", "nep", "", "Nepali"),
+    ("ng", "ndo", "", "Ndonga"),
+    ("nl", "nld", "dut", "Dutch"),
+    ("nn", "nno", "", "Norwegian Nynorsk"),
+    ("no", "nor", "", "Norwegian"),
+    ("nr", "nbl", "", "South Ndebele"),
+    ("nv", "nav", "", "Navajo"),
+    ("ny", "nya", "", "Chichewa"),
+    ("oc", "oci", "", "Occitan"),
+    ("oj", "oji", "", "Ojibwa"),
+    ("om", "orm", "", "Oromo"),
+    ("or", "ori", "", "Oriya"),
+    ("os", "oss", "", "Ossetian"),
+    ("pa", "pan", "", "Punjabi"),
+    ("pi", "pli", "", "Pali"),
+    ("pl", "pol", "", "Polish"),
+    ("ps", "pus", "", "Pashto"),
+    ("pt", "por", "", "Portuguese"),
+    ("qu", "que", "", "Quechua"),
+    ("rm", "roh", "", "Romansh"),
+    ("rn", "run", "", "Rundi"),
+    ("ro", "ron", "rum", "Romanian"),
+    ("ru", "rus", "", "Russian"),
+    ("rw", "kin", "", "Kinyarwanda"),
+    ("sa", "san", "", "Sanskrit"),
+    ("sc", "srd", "", "Sardinian"),
+    ("sd", "snd", "", "Sindhi"),
+    ("se", "sme", "", "Northern Sami"),
+    ("sg", "sag", "", "Sango"),
+    ("si", "sin", "", "Sinhala"),
+    ("sk", "slk", "slo", "Slovak"),
+    ("sl", "slv", "", "Slovenian"),
+    ("sm", "smo", "", "Samoan"),
+    ("sn", "sna", "", "Shona"),
+    ("so", "som", "", "Somali"),
+    ("sq", "sqi", "alb", "Albanian"),
+    ("sr", "srp", "", "Serbian"),
+    ("ss", "ssw", "", "Swati"),
+    ("st", "sot", "", "Southern Sotho"),
+    ("su", "sun", "", "Sundanese"),
+    ("sv", "swe", "", "Swedish"),
+    ("sw", "swa", "", "Swahili"),
+    ("ta", "tam", "", "Tamil"),
+    ("te", "tel", "", "Telugu"),
+    ("tg", "tgk", "", "Tajik"),
+    ("th", "tha", "", "Thai"),
+    ("ti", "tir", "", "Tigrinya"),
+    ("tk", "tuk", "", "Turkmen"),
+    ("tl", "tgl", "", "Tagalog"),
+    ("tn", "tsn", "", "Tswana"),
+    ("to", "ton", "", "Tongan"),
+    ("tr", "tur", "", "Turkish"),
+    ("ts", "tso", "", "Tsonga"),
+    ("tt", "tat", "", "Tatar"),
+    ("tw", "twi", "", "Twi"),
+    ("ty", "tah", "", "Tahitian"),
+    ("ug", "uig", "", "Uyghur"),
+    ("uk", "ukr", "", "Ukrainian"),
+    ("ur", "urd", "", "Urdu"),
+    ("uz", "uzb", "", "Uzbek"),
+    ("ve", "ven", "", "Venda"),
+    ("vi", "vie", "", "Vietnamese"),
+    ("vo", "vol", "", "Volapük"),
+    ("wa", "wln", "", "Walloon"),
+    ("wo", "wol", "", "Wolof"),
+    ("xh", "xho", "", "Xhosa"),
+    ("yi", "yid", "", "Yiddish"),
+    ("yo", "yor", "", "Yoruba"),
+    ("za", "zha", "", "Zhuang"),
+    ("zh", "zho", "chi", "Chinese"),
+    ("zu", "zul", "", "Zulu"),
+    ("", "ang", "", "Old English"),
+    ("", "arc", "", "Aramaic"),
+    ("", "ast", "", "Asturian"),
+    ("", "ber", "", "Berber"),
+    ("", "bho", "", "Bhojpuri"),
+    ("", "ceb", "", "Cebuano"),
+    ("", "chr", "", "Cherokee"),
+    ("", "csb", "", "Kashubian"),
+    ("", "dsb", "", "Lower Sorbian"),
+    ("", "fil", "", "Filipino"),
+    ("", "frr", "", "Northern Frisian"),
+    ("", "grc", "", "Ancient Greek"),
+    ("", "gsw", "", "Swiss German"),
+    ("", "haw", "", "Hawaiian"),
+    ("", "hmn", "", "Hmong"),
+    ("", "hsb", "", "Upper Sorbian"),
+    ("", "jbo", "", "Lojban"),
+    ("", "kab", "", "Kabyle"),
+    ("", "kok", "", "Konkani"),
+    ("", "lad", "", "Ladino"),
+    ("", "mai", "", "Maithili"),
+    ("", "mni", "", "Manipuri"),
+    ("", "nap", "", "Neapolitan"),
+    ("", "nds", "", "Low German"),
+    ("", "sah", "", "Yakut"),
+    ("", "sat", "", "Santali"),
+    ("", "scn", "", "Sicilian"),
+    ("", "sco", "", "Scots"),
+    ("", "syr", "", "Syriac"),
+    ("", "tet", "", "Tetum"),
+    ("", "tlh", "", "Klingon"),
+    ("", "mul", "", "Multiple languages"),
+    ("", "und", "", "Undetermined"),
+    ("", "zxx", "", "No linguistic content"),
+];
+
+// Codes commonly used for a language which are not ISO 639 codes (often
+// country codes, or withdrawn ISO 639-1 codes), and the code which was meant
+const LANGUAGE_MISTAKES: [(&str, &str); 14] = [
+    ("jp", "ja"),
+    ("gr", "el"),
+    ("dk", "da"),
+    ("cz", "cs"),
+    ("kr", "ko"),
+    ("cn", "zh"),
+    ("ua", "uk"),
+    ("vn", "vi"),
+    ("sp", "es"),
+    ("iw", "he"),
+    ("in", "id"),
+    ("ji", "yi"),
+    ("jw", "jv"),
+    ("mo", "ro"),
+];
+
+// ISO 3166-1 alpha-2 country codes, as used for BCP 47 regions, along with
+// Kosovo's user-assigned code
+const REGIONS: &str = "AD AE AF AG AI AL AM AO AQ AR AS AT AU AW AX AZ BA BB BD BE BF BG BH BI BJ \
+    BL BM BN BO BQ BR BS BT BV BW BY BZ CA CC CD CF CG CH CI CK CL CM CN CO CR CU CV CW CX CY CZ \
+    DE DJ DK DM DO DZ EC EE EG EH ER ES ET FI FJ FK FM FO FR GA GB GD GE GF GG GH GI GL GM GN GP \
+    GQ GR GS GT GU GW GY HK HM HN HR HT HU ID IE IL IM IN IO IQ IR IS IT JE JM JO JP KE KG KH KI \
+    KM KN KP KR KW KY KZ LA LB LC LI LK LR LS LT LU LV LY MA MC MD ME MF MG MH MK ML MM MN MO MP \
+    MQ MR MS MT MU MV MW MX MY MZ NA NC NE NF NG NI NL NO NP NR NU NZ OM PA PE PF PG PH PK PL PM \
+    PN PR PS PT PW PY QA RE RO RS RU RW SA SB SC SD SE SG SH SI SJ SK SL SM SN SO SR SS ST SV SX \
+    SY SZ TC TD TF TG TH TJ TK TL TM TN TO TR TT TV TW TZ UA UG UM US UY UZ VA VC VE VG VI VN VU \
+    WF WS XK YE YT ZA ZM ZW";
+
+const REGION_MISTAKES: [(&str, &str); 1] = [("UK", "GB")];
+
+// Find a language by any of its codes, or by its English name
+pub fn find_language(lang: &str) -> Option<&'static Language> {
+    let lang = lang.to_lowercase();
+    LANGUAGES.iter().find(|l| {
+        l.iso1 == Some(lang.as_str())
+            || l.iso2t == lang
+            || l.iso2b == Some(lang.as_str())
+            || l.name.to_lowercase() == lang
+    })
+}
+
+// The code we would expect a language to be written as
+pub fn preferred_code(language: &Language) -> &'static str {
+    language.iso1.unwrap_or(language.iso2t)
+}
+
+// The English name of a language, given any of its codes
+pub fn language_name(lang: &str) -> Option<&'static str> {
+    find_language(lang).map(|l| l.name)
+}
+
+// Whether a (lower case) code is how we would expect a language to be written
+pub fn is_preferred_language(lang: &str) -> bool {
+    find_language(lang).is_some_and(|l| preferred_code(l) == lang)
+}
+
+// Suggest the preferred code for something which is not a preferred language
+// code but which we recognise as a language (i.e., a three-letter code for a
+// language with a two-letter code, a language name, or a common mistake)
+pub fn suggest_language(lang: &str) -> Option<&'static str> {
+    let lang = lang.to_lowercase();
+    if let Some((_, code)) = LANGUAGE_MISTAKES.iter().find(|(m, _)| *m == lang) {
+        return Some(code);
+    }
+    find_language(&lang)
+        .map(preferred_code)
+        .filter(|code| *code != lang)
+}
+
+pub fn is_region(region: &str) -> bool {
+    REGIONS.split_whitespace().any(|r| r == region)
+}
+
+pub fn suggest_region(region: &str) -> Option<&'static str> {
+    REGION_MISTAKES
+        .iter()
+        .find(|(m, _)| *m == region)
+        .map(|(_, r)| *r)
+}
@@ -2,14 +2,40 @@ use clap::{crate_authors, crate_name, crate_version, ArgAction, Parser, Subcomma
 use colored::*;
 use std::{collections::HashMap, env, fs, path::PathBuf, process};
 
+mod ass;
 mod constants;
+mod convert;
 mod count;
+mod coverage;
+mod cue;
+mod diagnostics;
 mod dir;
+mod duplicates;
+mod ebml;
+mod encoding;
 mod episodes;
+mod extract;
+mod integrity;
+mod junk;
+mod langdetect;
+mod locale;
+mod manifest;
+mod media;
+mod mkv;
+mod mp4;
 mod path;
+mod quality;
 mod seasons;
+mod shift;
+mod smi;
+mod srt;
+mod strip;
+mod structure;
 mod subtitles;
 mod titles;
+mod unclaimed;
+mod validate;
+mod vtt;
 
 // TODO: clean up old code and make parts of this modular
 
@@ -67,7 +93,7 @@ struct Cli {
     )]
     consecutive_seasons: Option<bool>,
 
-    /// Check if series have all episodes in each season.  Use this flag with -f or -s
+    /// Check if film or series have correctly-formatted subtitles.  Use -f or -s to look in only one library
     #[arg(
         short = 'C',  // closed captions
         long = "subtitles",
@@ -76,8 +102,16 @@ struct Cli {
     )]
     subtitles: Option<bool>,
 
-    /// Check if film or series have correctly-formatted subtitles
-    // TODO: why is this called complete episodes?
+    /// With -C, rename badly-named subtitle files to their proposed names, after asking for confirmation
+    #[arg(
+        long = "fix",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+        requires = "subtitles",
+    )]
+    fix: Option<bool>,
+
+    /// Check if series have all episodes in each season.  Uses the expected-episode manifest (.filmls.toml), if any, to find missing trailing episodes and seasons
     #[arg(
         short = 'e',
         long = "complete-episodes",
@@ -86,6 +120,68 @@ struct Cli {
     )]
     complete_episodes: Option<bool>,
 
+    /// Check if any season contains the same episode more than once
+    #[arg(
+        short = 'd',
+        long = "duplicate-episodes",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+    )]
+    duplicate_episodes: Option<bool>,
+
+    /// Check if series directories are laid out correctly (i.e., episodes are in season directories)
+    #[arg(
+        short = 'L',
+        long = "structure",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+    )]
+    structure: Option<bool>,
+
+    /// List files which do not match any naming rule, grouped by extension and directory.  Use -f or -s to look in only one library
+    #[arg(
+        short = 'u',
+        long = "unclaimed",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+    )]
+    unclaimed: Option<bool>,
+
+    /// List junk files (OS metadata, partial downloads, and samples).  Use -f or -s to look in only one library
+    #[arg(
+        short = 'j',
+        long = "junk",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+    )]
+    junk: Option<bool>,
+
+    /// Check that media files are not empty or truncated, and that their contents match their extension.  Use -f or -s to look in only one library
+    #[arg(
+        short = 'i',
+        long = "integrity",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+    )]
+    integrity: Option<bool>,
+
+    /// Break down films and episodes by resolution (SD, 720p, 1080p, or 4K) and whether they are HDR, listing the lowest quality first.  Use -f or -s to look in only one library
+    #[arg(
+        short = 'Q',
+        long = "quality",
+        action = ArgAction::SetTrue,
+        num_args = 0,
+    )]
+    quality: Option<bool>,
+
+    /// Extensions or file names to ignore when listing unclaimed files (e.g., --ignore nfo,jpg)
+    #[arg(
+        long = "ignore",
+        action = ArgAction::Append,
+        value_delimiter = ',',
+    )]
+    ignore: Vec<String>,
+
     /// Takes an input directory.  Omitting this parameter, the programme will attempt to find the media directory
     #[arg(
         action = ArgAction::Set,
@@ -101,6 +197,99 @@ struct Cli {
 enum Command {
     /// Print the media directory the programme will use
     MediaDir,
+    /// Show the duration, resolution, codecs, and track languages of media files
+    Info {
+        /// A media file, or a directory to search for media files
+        path: PathBuf,
+    },
+    /// Move junk files (see -j) into a quarantine directory.  Use -f or -s to look in only one library
+    Clean {
+        /// Show which files would be moved, without moving them
+        #[arg(short = 'n', long = "dry-run")]
+        dry_run: bool,
+
+        /// Directory to move junk files into.  Defaults to .quarantine in the media directory
+        #[arg(short = 'q', long = "quarantine")]
+        quarantine: Option<PathBuf>,
+
+        /// Takes an input directory.  Omitting this parameter, the programme will attempt to find the media directory
+        dir: Option<PathBuf>,
+    },
+    /// Work with the contents of subtitle files
+    Subtitles {
+        #[command(subcommand)]
+        command: SubtitlesCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum SubtitlesCommand {
+    /// Check subtitle files for malformed or badly-timed cues.  Use -f or -s to look in only one library
+    Validate {
+        /// Subtitle files, or directories to search for subtitle files.  Omitting this parameter, the programme will search the media directory
+        paths: Vec<PathBuf>,
+    },
+    /// Report subtitle files which are not encoded as UTF-8.  Use -f or -s to look in only one library
+    Encoding {
+        /// Rewrite the files as UTF-8, keeping a backup of each original (with a .bak extension)
+        #[arg(long = "fix")]
+        fix: bool,
+
+        /// Subtitle files, or directories to search for subtitle files.  Omitting this parameter, the programme will search the media directory
+        paths: Vec<PathBuf>,
+    },
+    /// Convert subtitle files between SRT, WebVTT, ASS, SSA, and SAMI, keeping their names.  Use -f or -s to look in only one library
+    Convert {
+        /// Format to convert to: srt, vtt, ass, ssa, or smi
+        #[arg(short = 't', long = "to")]
+        to: String,
+
+        /// Subtitle files, or directories to search for subtitle files.  Omitting this parameter, the programme will search the media directory
+        paths: Vec<PathBuf>,
+    },
+    /// Move every cue in subtitle files by an offset, or from one framerate to another.  Files are changed in place, keeping a backup (with a .bak extension)
+    Shift {
+        /// Offset to add to every cue, in seconds (e.g., 2.5 or -1.2) or as a timestamp (e.g., -00:00:01,200)
+        #[arg(long = "offset", allow_hyphen_values = true)]
+        offset: Option<String>,
+
+        /// Framerate the subtitles were timed for, and the framerate of the video, as FROM:TO (e.g., 25:23.976)
+        #[arg(long = "fps")]
+        fps: Option<String>,
+
+        /// Write the shifted subtitles to this file, rather than changing the original.  Only with a single subtitle file
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+
+        /// Subtitle files, or directories to search for subtitle files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Extract the text subtitle tracks (SRT, ASS, and SSA) embedded in Matroska files into sidecar files, named with each track's language
+    Extract {
+        /// Matroska files, or directories to search for them.  Omitting this parameter, the programme will search the media directory
+        paths: Vec<PathBuf>,
+    },
+    /// Remove sound cues and speaker labels from subtitle files, writing each alongside the original as the plain (not SDH or CC) variant.  An original with the plain name is renamed to the SDH variant, after asking
+    StripHi {
+        /// Subtitle files, or directories to search for subtitle files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Report subtitle files whose text appears to be in a different language to their name
+    Language {
+        /// Subtitle files, or directories to search for subtitle files.  Omitting this parameter, the programme will search the media directory
+        paths: Vec<PathBuf>,
+    },
+    /// Report which films and episodes have subtitles in a language, with coverage per library, series, and season.  Use -f or -s to look in only one library
+    Coverage {
+        /// Language to report coverage for, as an ISO 639 code
+        #[arg(short = 'l', long = "language", default_value = "en")]
+        language: String,
+
+        /// Takes an input directory.  Omitting this parameter, the programme will attempt to find the media directory
+        dir: Option<PathBuf>,
+    },
 }
 
 // Main function
@@ -116,12 +305,14 @@ fn main() {
         &dir::get_media_dir()
     };
 
-    match cli.command {
-        Some(Command::MediaDir) => {
-            println!("{}", dirname.display());
-            process::exit(0);
-        }
-        None => {}
+    if let Some(Command::MediaDir) = cli.command {
+        println!("{}", dirname.display());
+        process::exit(0);
+    }
+
+    if let Some(Command::Info { ref path }) = cli.command {
+        media::show_media_info(path);
+        process::exit(0);
     }
 
     // Check that not both -s and -f are present
@@ -142,10 +333,10 @@ fn main() {
             media_type = Some(dir::MediaType::Series);
         }
     }
-    if (cli.films, cli.series) == (Some(false), Some(false)) {
-        media_type = Some(dir::MediaType::Impossible);
-    }
-    if media_type.is_none() && cli.films.is_none() && cli.series.is_none() {
+    // As the flags are set with `SetTrue`, they are `Some(false)` when not given
+    if (cli.films, cli.series) == (Some(false), Some(false))
+        || (cli.films.is_none() && cli.series.is_none())
+    {
         media_type = Some(dir::MediaType::Unknown);
     }
     let media_type = media_type.unwrap_or_else(|| {
@@ -155,6 +346,66 @@ fn main() {
         )
     });
 
+    // Subcommands which need the media type
+    if let Some(Command::Clean {
+        dry_run,
+        ref quarantine,
+        ref dir,
+    }) = cli.command
+    {
+        let dirname = dir.as_ref().unwrap_or(dirname);
+        junk::clean_junk_files(dirname, &media_type, quarantine.as_deref(), dry_run);
+        process::exit(0);
+    }
+    if let Some(Command::Subtitles { ref command }) = cli.command {
+        // Subtitle commands take paths to files or directories, defaulting to
+        // the libraries in the media directory
+        let library_dirs = |paths: &[PathBuf]| -> Vec<PathBuf> {
+            if paths.is_empty() {
+                media_type
+                    .libraries()
+                    .iter()
+                    .map(|library| dirname.join(library.as_str()))
+                    .collect()
+            } else {
+                paths.to_vec()
+            }
+        };
+        match command {
+            SubtitlesCommand::Validate { paths } => {
+                validate::validate_subtitles(&library_dirs(paths));
+            }
+            SubtitlesCommand::Encoding { fix, paths } => {
+                encoding::check_subtitle_encodings(&library_dirs(paths), *fix);
+            }
+            SubtitlesCommand::Convert { to, paths } => {
+                convert::convert_subtitles(&library_dirs(paths), to);
+            }
+            SubtitlesCommand::Shift {
+                offset,
+                fps,
+                output,
+                paths,
+            } => {
+                shift::shift_subtitles(paths, offset.as_deref(), fps.as_deref(), output.as_deref());
+            }
+            SubtitlesCommand::Extract { paths } => {
+                extract::extract_subtitles(&library_dirs(paths));
+            }
+            SubtitlesCommand::StripHi { paths } => {
+                strip::strip_hi_subtitles(paths);
+            }
+            SubtitlesCommand::Language { paths } => {
+                langdetect::check_subtitle_languages(&library_dirs(paths));
+            }
+            SubtitlesCommand::Coverage { language, dir } => {
+                let dirname = dir.as_ref().unwrap_or(dirname);
+                coverage::show_subtitle_coverage(dirname, &media_type, language);
+            }
+        }
+        process::exit(0);
+    }
+
     // List films
     // If no arguments are passed, will list
     if std::env::args().len() <= 1 {
@@ -198,7 +449,10 @@ fn main() {
                     .unwrap();
                 film_map.insert(film_name, film_year);
             } else {
-                eprintln!("Warning: film \"{}\" does not match regex", &film_name)
+                eprintln!("Warning: film \"{}\" does not match regex", &film_name);
+                if let Some(diagnostic) = diagnostics::diagnose_film(&film_name) {
+                    diagnostic.eprint(1);
+                }
             }
         }
 
@@ -234,25 +488,60 @@ fn main() {
         }
     }
 
-    // TODO: Check that no episodes are missing from any given season
+    // Check that no episodes are missing from any given season
     if let Some(check_complete_episodes) = cli.complete_episodes {
         if check_complete_episodes {
             episodes::check_complete_episodes(dirname);
         }
     }
 
+    // Alert on episodes which appear more than once in a season
+    if let Some(check_duplicate_episodes) = cli.duplicate_episodes {
+        if check_duplicate_episodes {
+            duplicates::check_duplicate_episodes(dirname);
+        }
+    }
+
+    // Report series directories which are empty or contain unexpected files
+    if let Some(check_structure) = cli.structure {
+        if check_structure {
+            structure::check_series_structure(dirname);
+        }
+    }
+
+    // List files which no naming rule recognises
+    if let Some(list_unclaimed) = cli.unclaimed {
+        if list_unclaimed {
+            unclaimed::list_unclaimed_files(dirname, &media_type, &cli.ignore);
+        }
+    }
+
+    // List files which should not be in the library
+    if let Some(list_junk) = cli.junk {
+        if list_junk {
+            junk::list_junk_files(dirname, &media_type);
+        }
+    }
+
+    // Check media files are what they say they are, and are whole
+    if let Some(check_integrity) = cli.integrity {
+        if check_integrity {
+            integrity::check_media_integrity(dirname, &media_type);
+        }
+    }
+
+    // Break down the libraries by video quality
+    if let Some(show_quality) = cli.quality {
+        if show_quality {
+            quality::show_quality_breakdown(dirname, &media_type);
+        }
+    }
+
     // Check subtitle format
     // https://github.com/G-Street/media-scripts/blob/4dfc232d/plex/format.md#subtitles
     if let Some(check_subtitles) = cli.subtitles {
         if check_subtitles {
-            let films_only = (cli.films, cli.series) == (Some(true), Some(false));
-            let series_only = (cli.films, cli.series) == (Some(false), Some(true));
-            if !(films_only || series_only) {
-                eprintln!("[ERROR] Must use -f or -s with -C");
-                process::exit(1);
-            }
-            // TODO: print what the correct format should be?
-            subtitles::list_erroneous_subtitles(dirname, &media_type)
+            subtitles::list_erroneous_subtitles(dirname, &media_type, cli.fix == Some(true))
         }
     }
 }
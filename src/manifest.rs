@@ -0,0 +1,182 @@
+// This file provides functions for reading the expected-episode manifest.
+//
+// A manifest records how many episodes each season of a series should have,
+// and whether the series has ended.  It can live in a central file in the
+// series directory, with one table per series:
+//
+//     ["Breaking Bad"]
+//     ended = true
+//     episodes = [7, 13, 13, 13, 16]
+//
+// or in a sidecar file inside the series' own directory, without the table
+// header.  The sidecar takes precedence over the central manifest.  The nth
+// element of `episodes` is the number of episodes in season n.
+//
+// Series which use absolute episode numbering (common for anime) should set
+// `absolute = true`; their episodes are then numbered across the whole series,
+// and `episodes` is summed to get the expected total.
+//
+// Only the small subset of TOML needed for the above is understood.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::dir::MediaType;
+
+pub const MANIFEST_FILE_NAME: &str = ".filmls.toml";
+
+#[derive(Clone, Default)]
+pub struct SeriesManifest {
+    pub ended: bool,
+    // Episodes are numbered across the whole series rather than per season
+    pub absolute: bool,
+    // Expected episode count for each season, starting from season 1
+    pub episodes: Vec<isize>,
+}
+
+impl SeriesManifest {
+    pub fn expected_episodes(&self, season_num: isize) -> Option<isize> {
+        if season_num < 1 {
+            return None;
+        }
+        self.episodes.get((season_num - 1) as usize).copied()
+    }
+
+    pub fn season_count(&self) -> isize {
+        self.episodes.len() as isize
+    }
+
+    // The final season of a series that has not ended may still be airing,
+    // so we cannot yet know which of its episodes are missing
+    pub fn is_complete_season(&self, season_num: isize) -> bool {
+        self.ended || season_num < self.season_count()
+    }
+
+    // The expected number of episodes across the whole series, if it has ended
+    pub fn expected_total(&self) -> Option<isize> {
+        if self.ended && !self.episodes.is_empty() {
+            Some(self.episodes.iter().sum())
+        } else {
+            None
+        }
+    }
+}
+
+enum Value {
+    Bool(bool),
+    IntArray(Vec<isize>),
+}
+
+pub struct Manifest {
+    series: HashMap<String, SeriesManifest>,
+}
+
+impl Manifest {
+    // Load the central manifest from the series directory (if one exists)
+    pub fn load(dirname: &Path) -> Self {
+        let mut path = dirname.to_path_buf();
+        path.push(MediaType::Series.as_str());
+        path.push(MANIFEST_FILE_NAME);
+        let series = match fs::read_to_string(&path) {
+            Ok(s) => parse(&s, &path),
+            Err(_) => HashMap::new(),
+        };
+        Manifest { series }
+    }
+
+    // Look up a series, preferring a sidecar manifest in the series' directory
+    pub fn get(&self, series_path: &Path) -> Option<SeriesManifest> {
+        let mut sidecar = series_path.to_path_buf();
+        sidecar.push(MANIFEST_FILE_NAME);
+        if let Ok(s) = fs::read_to_string(&sidecar) {
+            return parse(&s, &sidecar).remove("");
+        }
+        let series_name = series_path.file_name()?.to_str()?;
+        self.series.get(series_name).cloned()
+    }
+}
+
+fn parse(contents: &str, path: &Path) -> HashMap<String, SeriesManifest> {
+    let mut series = HashMap::<String, SeriesManifest>::new();
+    // Keys before any table header belong to the sidecar's own series
+    let mut current = String::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let warn = |msg: &str| {
+            eprintln!(
+                "[WARN] {}:{}: {}; ignoring line",
+                path.display(),
+                i + 1,
+                msg
+            )
+        };
+        if line.starts_with('[') && line.ends_with(']') {
+            current = unquote(line[1..line.len() - 1].trim()).to_string();
+            series.entry(current.clone()).or_default();
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((k, v)) => (unquote(k.trim()), v.trim()),
+            None => {
+                warn("expected \"key = value\"");
+                continue;
+            }
+        };
+        let value = match parse_value(value) {
+            Some(value) => value,
+            None => {
+                warn(&format!("cannot parse value of \"{}\"", key));
+                continue;
+            }
+        };
+        let entry = series.entry(current.clone()).or_default();
+        match (key, value) {
+            ("ended", Value::Bool(b)) => entry.ended = b,
+            ("absolute", Value::Bool(b)) => entry.absolute = b,
+            ("episodes", Value::IntArray(v)) => entry.episodes = v,
+            ("ended", _) | ("absolute", _) | ("episodes", _) => {
+                warn(&format!("wrong type for \"{}\"", key))
+            }
+            _ => warn(&format!("unknown key \"{}\"", key)),
+        }
+    }
+    series
+}
+
+fn parse_value(value: &str) -> Option<Value> {
+    match value {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        _ => {}
+    }
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    inner
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<isize>().ok())
+        .collect::<Option<Vec<_>>>()
+        .map(Value::IntArray)
+}
+
+// Remove a trailing comment, taking care not to cut a quoted series name
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
@@ -0,0 +1,186 @@
+// This file provides the properties of media files (duration, resolution,
+// codecs, and the languages of their tracks), as read from their containers
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use colored::*;
+
+use super::dir;
+use super::locale;
+use super::mkv;
+use super::mp4;
+use super::path;
+
+pub struct MediaInfo {
+    // The name of the container format (e.g., "Matroska")
+    pub container: &'static str,
+    // In seconds
+    pub duration: Option<f64>,
+    pub video: Vec<VideoTrack>,
+    pub audio: Vec<AudioTrack>,
+    pub subtitles: Vec<SubtitleTrack>,
+}
+
+pub struct VideoTrack {
+    // The codec as the container identifies it (e.g., "V_MPEG4/ISO/AVC" or
+    // "avc1")
+    pub codec: String,
+    pub width: u64,
+    pub height: u64,
+    pub hdr: bool,
+}
+
+pub struct AudioTrack {
+    pub codec: String,
+    // The language as the container gives it (e.g., "eng" or "en-GB"), if known
+    pub language: Option<String>,
+    pub channels: Option<u64>,
+}
+
+pub struct SubtitleTrack {
+    pub codec: String,
+    pub language: Option<String>,
+    pub forced: bool,
+}
+
+// Read the properties of a media file, if we can read its container
+pub fn read_media_info(p: &Path) -> io::Result<MediaInfo> {
+    let ext = path::get_extension_from_filename(p)
+        .unwrap_or_default()
+        .to_lowercase();
+    match ext.as_str() {
+        "mkv" | "webm" => mkv::read_info(p),
+        "mp4" | "m4v" | "mov" => mp4::read_info(p),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("cannot read .{} files", ext),
+        )),
+    }
+}
+
+// Show the properties of media files.  Directories are searched recursively
+pub fn show_media_info(p: &Path) {
+    let files: Vec<PathBuf> = if p.is_dir() {
+        dir::list_files(p)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| path::is_media_file(p))
+            .collect()
+    } else if p.exists() {
+        vec![p.to_path_buf()]
+    } else {
+        eprintln!("No such file or directory: {:?}", p);
+        process::exit(1);
+    };
+    for file in files {
+        let info = match read_media_info(&file) {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", file, e);
+                continue;
+            }
+        };
+        println!("{}", file.display().to_string().bold());
+        println!("\t{}{}", "Container: ".italic(), info.container);
+        if let Some(duration) = info.duration {
+            println!("\t{}{}", "Duration: ".italic(), format_duration(duration));
+        }
+        for video in &info.video {
+            println!(
+                "\t{}{} {}x{}{}",
+                "Video: ".italic(),
+                codec_name(&video.codec),
+                video.width,
+                video.height,
+                if video.hdr { " (HDR)" } else { "" }
+            );
+        }
+        for audio in &info.audio {
+            let channels = audio
+                .channels
+                .map(|c| format!(" {} channels", c))
+                .unwrap_or_default();
+            println!(
+                "\t{}{}{} [{}]",
+                "Audio: ".italic(),
+                codec_name(&audio.codec),
+                channels,
+                language_name(audio.language.as_deref())
+            );
+        }
+        for sub in &info.subtitles {
+            println!(
+                "\t{}{} [{}]{}",
+                "Subtitles: ".italic(),
+                codec_name(&sub.codec),
+                language_name(sub.language.as_deref()),
+                if sub.forced { " (forced)" } else { "" }
+            );
+        }
+    }
+}
+
+// A readable name for a codec, given its identifier in the container
+pub fn codec_name(codec: &str) -> &str {
+    match codec {
+        "V_MPEG4/ISO/AVC" => "H.264",
+        "V_MPEGH/ISO/HEVC" => "HEVC",
+        "V_AV1" => "AV1",
+        "V_VP8" => "VP8",
+        "V_VP9" => "VP9",
+        "V_MPEG2" => "MPEG-2",
+        "V_MPEG4/ISO/ASP" => "MPEG-4",
+        "A_AAC" | "A_AAC/MPEG4/LC" | "A_AAC/MPEG2/LC" => "AAC",
+        "A_AC3" => "AC-3",
+        "A_EAC3" => "E-AC-3",
+        "A_DTS" => "DTS",
+        "A_TRUEHD" => "TrueHD",
+        "A_FLAC" => "FLAC",
+        "A_OPUS" => "Opus",
+        "A_VORBIS" => "Vorbis",
+        "A_MPEG/L3" => "MP3",
+        "S_TEXT/UTF8" => "SRT",
+        "S_TEXT/ASS" => "ASS",
+        "S_TEXT/SSA" => "SSA",
+        "S_TEXT/WEBVTT" => "WebVTT",
+        "S_HDMV/PGS" => "PGS",
+        "S_VOBSUB" => "VobSub",
+        "avc1" | "avc3" => "H.264",
+        "hvc1" | "hev1" => "HEVC",
+        "dvh1" | "dvhe" => "HEVC (Dolby Vision)",
+        "av01" => "AV1",
+        "vp09" => "VP9",
+        "mp4v" => "MPEG-4",
+        "mp4a" => "AAC",
+        "ac-3" => "AC-3",
+        "ec-3" => "E-AC-3",
+        "Opus" => "Opus",
+        "fLaC" => "FLAC",
+        "tx3g" => "Timed text",
+        "wvtt" => "WebVTT",
+        "c608" => "CEA-608",
+        codec => codec,
+    }
+}
+
+fn language_name(language: Option<&str>) -> String {
+    match language {
+        Some(language) => {
+            let lang = language.split('-').next().unwrap_or(language);
+            locale::language_name(lang).unwrap_or(language).to_string()
+        }
+        None => "Unknown language".to_string(),
+    }
+}
+
+// Format a duration in seconds as "H:MM:SS"
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
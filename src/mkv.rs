@@ -0,0 +1,440 @@
+// This file provides a demuxer for Matroska (and WebM) files: reading their
+// tracks, and the blocks of the tracks we want (e.g., subtitles) from their
+// clusters.
+//
+// See the Matroska specification (https://www.matroska.org/technical/elements.html)
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use super::ebml::{self, Reader};
+use super::media::{AudioTrack, MediaInfo, SubtitleTrack, VideoTrack};
+
+const EBML: u32 = 0x1A45DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x18538067;
+const SEEK_HEAD: u32 = 0x114D9B74;
+const INFO: u32 = 0x1549A966;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const DURATION: u32 = 0x4489;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const LANGUAGE: u32 = 0x22B59C;
+const LANGUAGE_BCP47: u32 = 0x22B59D;
+const NAME: u32 = 0x536E;
+const FLAG_FORCED: u32 = 0x55AA;
+const FLAG_HEARING_IMPAIRED: u32 = 0x55AB;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const COLOUR: u32 = 0x55B0;
+const TRANSFER_CHARACTERISTICS: u32 = 0x55BA;
+const AUDIO: u32 = 0xE1;
+const CHANNELS: u32 = 0x9F;
+const CLUSTER: u32 = 0x1F43B675;
+const TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const BLOCK_DURATION: u32 = 0x9B;
+const CUES: u32 = 0x1C53BB6B;
+const ATTACHMENTS: u32 = 0x1941A469;
+const CHAPTERS: u32 = 0x1043A770;
+const TAGS: u32 = 0x1254C367;
+
+// Elements which may follow a cluster in a segment, and so end a cluster of
+// unknown size
+const SEGMENT_CHILDREN: [u32; 8] = [
+    SEEK_HEAD,
+    INFO,
+    TRACKS,
+    CLUSTER,
+    CUES,
+    ATTACHMENTS,
+    CHAPTERS,
+    TAGS,
+];
+
+pub const TRACK_TYPE_VIDEO: u64 = 0x01;
+pub const TRACK_TYPE_AUDIO: u64 = 0x02;
+pub const TRACK_TYPE_SUBTITLE: u64 = 0x11;
+
+// Transfer characteristics (from ITU-T H.273) of HDR video: SMPTE ST 2084
+// (PQ, as used by HDR10 and Dolby Vision) and ARIB STD-B67 (HLG)
+const TRANSFER_PQ: u64 = 16;
+const TRANSFER_HLG: u64 = 18;
+
+pub struct Track {
+    pub number: u64,
+    pub kind: u64,
+    pub codec: String,
+    pub codec_private: Vec<u8>,
+    // The language as an ISO 639-2 code (e.g., "eng"), or a BCP 47 tag (e.g.,
+    // "en-GB") if the file has one
+    pub language: String,
+    pub name: Option<String>,
+    pub forced: bool,
+    pub hearing_impaired: bool,
+    // The duration of each block, in nanoseconds
+    pub default_duration: Option<u64>,
+    // Video tracks only
+    pub width: u64,
+    pub height: u64,
+    pub transfer: Option<u64>,
+    // Audio tracks only
+    pub channels: Option<u64>,
+}
+
+// A frame of a track (e.g., a single subtitle), with times in milliseconds
+pub struct Block {
+    pub track: u64,
+    pub start: i64,
+    pub duration: Option<i64>,
+    pub data: Vec<u8>,
+}
+
+// Read the tracks of a Matroska file, along with the blocks of the tracks for
+// which `wanted` is true
+pub fn demux(p: &Path, wanted: impl Fn(&Track) -> bool) -> io::Result<(Vec<Track>, Vec<Block>)> {
+    let mut reader = Reader::new(File::open(p)?)?;
+    let (_, segment) = read_segment_header(&mut reader)?;
+    let segment_end = segment.end().unwrap_or(u64::MAX).min(reader.len());
+    let mut scale = 1_000_000;
+    let mut tracks = Vec::new();
+    let mut wanted_tracks = Vec::new();
+    let mut blocks = Vec::new();
+    while reader.position() < segment_end {
+        let header = match reader.read_header()? {
+            Some(header) => header,
+            None => break,
+        };
+        match header.id {
+            INFO => {
+                let data = reader.read_data(&header)?;
+                for (id, value) in ebml::children(&data) {
+                    if id == TIMESTAMP_SCALE {
+                        scale = ebml::uint(value);
+                    }
+                }
+            }
+            TRACKS => {
+                tracks = read_tracks(&reader.read_data(&header)?);
+                wanted_tracks = tracks
+                    .iter()
+                    .filter(|t| wanted(t))
+                    .map(|t| t.number)
+                    .collect();
+            }
+            CLUSTER => {
+                let end = header.end();
+                read_cluster(&mut reader, end, scale, &wanted_tracks, &mut blocks)?;
+            }
+            _ => reader.skip(&header)?,
+        }
+    }
+    Ok((tracks, blocks))
+}
+
+// Read the properties of a Matroska file from its segment information and
+// tracks, without reading any clusters.  These normally come before the
+// clusters; if the tracks do not, the seek head says where to find them
+pub fn read_info(p: &Path) -> io::Result<MediaInfo> {
+    let mut reader = Reader::new(File::open(p)?)?;
+    let (doc_type, segment) = read_segment_header(&mut reader)?;
+    let segment_end = segment.end().unwrap_or(u64::MAX).min(reader.len());
+    let mut scale = 1_000_000;
+    let mut duration = None;
+    let mut tracks = None;
+    let mut tracks_position = None;
+    while reader.position() < segment_end && tracks.is_none() {
+        let header = match reader.read_header()? {
+            Some(header) => header,
+            None => break,
+        };
+        match header.id {
+            SEEK_HEAD => {
+                let data = reader.read_data(&header)?;
+                for (_, seek) in ebml::children(&data)
+                    .into_iter()
+                    .filter(|(id, _)| *id == SEEK)
+                {
+                    let seek = ebml::children(seek);
+                    let id = seek.iter().find(|(id, _)| *id == SEEK_ID);
+                    let position = seek.iter().find(|(id, _)| *id == SEEK_POSITION);
+                    if let (Some((_, id)), Some((_, position))) = (id, position) {
+                        if ebml::uint(id) == TRACKS as u64 {
+                            tracks_position = Some(segment.start + ebml::uint(position));
+                        }
+                    }
+                }
+            }
+            INFO => {
+                for (id, value) in ebml::children(&reader.read_data(&header)?) {
+                    match id {
+                        TIMESTAMP_SCALE => scale = ebml::uint(value),
+                        DURATION => duration = ebml::float(value),
+                        _ => {}
+                    }
+                }
+            }
+            TRACKS => tracks = Some(read_tracks(&reader.read_data(&header)?)),
+            CLUSTER => match tracks_position {
+                Some(position) if position > reader.position() => reader.seek(position)?,
+                _ => break,
+            },
+            _ => reader.skip(&header)?,
+        }
+    }
+    let tracks = tracks.ok_or_else(|| ebml::invalid_data("no tracks found"))?;
+    Ok(MediaInfo {
+        container: if doc_type == "webm" {
+            "WebM"
+        } else {
+            "Matroska"
+        },
+        // The duration is given in units of the timestamp scale (nanoseconds)
+        duration: duration.map(|d| d * scale as f64 / 1e9),
+        video: tracks
+            .iter()
+            .filter(|t| t.kind == TRACK_TYPE_VIDEO)
+            .map(|t| VideoTrack {
+                codec: t.codec.clone(),
+                width: t.width,
+                height: t.height,
+                hdr: t
+                    .transfer
+                    .is_some_and(|t| t == TRANSFER_PQ || t == TRANSFER_HLG),
+            })
+            .collect(),
+        audio: tracks
+            .iter()
+            .filter(|t| t.kind == TRACK_TYPE_AUDIO)
+            .map(|t| AudioTrack {
+                codec: t.codec.clone(),
+                language: known_language(&t.language),
+                channels: t.channels,
+            })
+            .collect(),
+        subtitles: tracks
+            .iter()
+            .filter(|t| t.kind == TRACK_TYPE_SUBTITLE)
+            .map(|t| SubtitleTrack {
+                codec: t.codec.clone(),
+                language: known_language(&t.language),
+                forced: t.forced,
+            })
+            .collect(),
+    })
+}
+
+// A track's language, unless it is undetermined
+fn known_language(language: &str) -> Option<String> {
+    if language.is_empty() || language == "und" {
+        None
+    } else {
+        Some(language.to_string())
+    }
+}
+
+// Check that a file is Matroska, and read its document type (i.e., "matroska"
+// or "webm") and the header of its segment, which holds everything else
+pub fn read_segment_header(reader: &mut Reader<File>) -> io::Result<(String, ebml::Header)> {
+    let doc_type = read_doc_type(reader)?;
+    loop {
+        match reader.read_header()? {
+            Some(header) if header.id == SEGMENT => return Ok((doc_type, header)),
+            Some(header) => reader.skip(&header)?,
+            None => return Err(ebml::invalid_data("no segment found")),
+        }
+    }
+}
+
+// Read the EBML header at the start of a file, which says what kind of
+// document it holds
+fn read_doc_type(reader: &mut Reader<File>) -> io::Result<String> {
+    let header = match reader.read_header() {
+        Ok(Some(header)) if header.id == EBML => header,
+        _ => return Err(ebml::invalid_data("not a Matroska file")),
+    };
+    let data = reader.read_data(&header)?;
+    let doc_type = ebml::children(&data)
+        .into_iter()
+        .find(|(id, _)| *id == DOC_TYPE)
+        .map(|(_, value)| ebml::string(value))
+        .unwrap_or_default();
+    if doc_type != "matroska" && doc_type != "webm" {
+        return Err(ebml::invalid_data(format!(
+            "not a Matroska file (document type \"{}\")",
+            doc_type
+        )));
+    }
+    Ok(doc_type)
+}
+
+fn read_tracks(data: &[u8]) -> Vec<Track> {
+    ebml::children(data)
+        .into_iter()
+        .filter(|(id, _)| *id == TRACK_ENTRY)
+        .map(|(_, entry)| {
+            let mut track = Track {
+                number: 0,
+                kind: 0,
+                codec: String::new(),
+                codec_private: Vec::new(),
+                // English is assumed when a track has no language
+                language: "eng".to_string(),
+                name: None,
+                forced: false,
+                hearing_impaired: false,
+                default_duration: None,
+                width: 0,
+                height: 0,
+                transfer: None,
+                channels: None,
+            };
+            let mut bcp47 = None;
+            for (id, value) in ebml::children(entry) {
+                match id {
+                    TRACK_NUMBER => track.number = ebml::uint(value),
+                    TRACK_TYPE => track.kind = ebml::uint(value),
+                    CODEC_ID => track.codec = ebml::string(value),
+                    CODEC_PRIVATE => track.codec_private = value.to_vec(),
+                    LANGUAGE => track.language = ebml::string(value),
+                    LANGUAGE_BCP47 => bcp47 = Some(ebml::string(value)),
+                    NAME => track.name = Some(ebml::string(value)),
+                    FLAG_FORCED => track.forced = ebml::uint(value) != 0,
+                    FLAG_HEARING_IMPAIRED => track.hearing_impaired = ebml::uint(value) != 0,
+                    DEFAULT_DURATION => track.default_duration = Some(ebml::uint(value)),
+                    VIDEO => read_video(value, &mut track),
+                    AUDIO => {
+                        for (id, value) in ebml::children(value) {
+                            if id == CHANNELS {
+                                track.channels = Some(ebml::uint(value));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(bcp47) = bcp47 {
+                track.language = bcp47;
+            }
+            track
+        })
+        .collect()
+}
+
+fn read_video(data: &[u8], track: &mut Track) {
+    for (id, value) in ebml::children(data) {
+        match id {
+            PIXEL_WIDTH => track.width = ebml::uint(value),
+            PIXEL_HEIGHT => track.height = ebml::uint(value),
+            COLOUR => {
+                for (id, value) in ebml::children(value) {
+                    if id == TRANSFER_CHARACTERISTICS {
+                        track.transfer = Some(ebml::uint(value));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Read the blocks of the wanted tracks from a cluster.  A cluster of unknown
+// size ends where the next element of the segment starts
+fn read_cluster(
+    reader: &mut Reader<File>,
+    end: Option<u64>,
+    scale: u64,
+    wanted: &[u64],
+    blocks: &mut Vec<Block>,
+) -> io::Result<()> {
+    let end = end.unwrap_or(u64::MAX).min(reader.len());
+    let mut timestamp = 0;
+    while reader.position() < end {
+        let start = reader.position();
+        let header = match reader.read_header()? {
+            Some(header) => header,
+            None => break,
+        };
+        if SEGMENT_CHILDREN.contains(&header.id) {
+            reader.seek(start)?;
+            break;
+        }
+        match header.id {
+            TIMESTAMP => timestamp = ebml::uint(&reader.read_data(&header)?),
+            SIMPLE_BLOCK => {
+                // Only read the whole block if it belongs to a track we want
+                let size = header.size.unwrap_or(0);
+                let peek = ebml::Header {
+                    size: Some(size.min(8)),
+                    ..header
+                };
+                let mut data = reader.read_data(&peek)?;
+                let track = ebml::vint(&data).map(|(track, _)| track);
+                if track.is_some_and(|t| wanted.contains(&t)) {
+                    let rest = ebml::Header {
+                        size: Some(size - size.min(8)),
+                        start: reader.position(),
+                        ..peek
+                    };
+                    data.extend(reader.read_data(&rest)?);
+                    blocks.extend(read_block(&data, timestamp, None, scale));
+                } else {
+                    reader.seek(peek.start + size)?;
+                }
+            }
+            BLOCK_GROUP => {
+                let data = reader.read_data(&header)?;
+                let children = ebml::children(&data);
+                let duration = children
+                    .iter()
+                    .find(|(id, _)| *id == BLOCK_DURATION)
+                    .map(|(_, value)| ebml::uint(value));
+                if let Some((_, block)) = children.iter().find(|(id, _)| *id == BLOCK) {
+                    blocks.extend(
+                        read_block(block, timestamp, duration, scale)
+                            .filter(|b| wanted.contains(&b.track)),
+                    );
+                }
+            }
+            _ => reader.skip(&header)?,
+        }
+    }
+    Ok(())
+}
+
+// Read a block, which starts with its track number, its time relative to the
+// cluster, and flags.  Laced blocks (several frames in one) are only used for
+// audio, so are ignored.  Times too large to hold in milliseconds (as only a
+// corrupt file could have) make the block unreadable
+fn read_block(data: &[u8], timestamp: u64, duration: Option<u64>, scale: u64) -> Option<Block> {
+    let (track, len) = ebml::vint(data)?;
+    let header = data.get(len..len + 3)?;
+    let relative = i16::from_be_bytes([header[0], header[1]]) as i64;
+    if header[2] & 0x06 != 0 {
+        return None;
+    }
+    let to_ms = |ticks: i64| Some(ticks.checked_mul(i64::try_from(scale).ok()?)? / 1_000_000);
+    let start = i64::try_from(timestamp).ok()?.checked_add(relative)?;
+    let duration = match duration {
+        Some(duration) => Some(to_ms(i64::try_from(duration).ok()?)?),
+        None => None,
+    };
+    Some(Block {
+        track,
+        start: to_ms(start)?,
+        duration,
+        data: data[len + 3..].to_vec(),
+    })
+}
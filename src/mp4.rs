@@ -0,0 +1,276 @@
+// This file provides a reader for the properties of MP4 and QuickTime files,
+// from the boxes (or "atoms") they are made of.  Each box is written as a size
+// and a four-character type followed by its data, which may itself be boxes.
+// Everything we need is in the movie box (moov); the media data (mdat) is
+// never read.
+//
+// See ISO/IEC 14496-12 and the QuickTime File Format Specification
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::media::{AudioTrack, MediaInfo, SubtitleTrack, VideoTrack};
+
+// The largest movie box we will read into memory
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+// The header of a box in a file
+pub struct BoxHeader {
+    pub kind: [u8; 4],
+    // The position of the box's data, and its size
+    pub start: u64,
+    pub size: u64,
+}
+
+impl BoxHeader {
+    // The position of the end of the box, unless its size is too large for
+    // that to be a position in any file
+    pub fn end(&self) -> Option<u64> {
+        self.start.checked_add(self.size)
+    }
+}
+
+// Read the headers of the top-level boxes of a file.  The last may run past
+// the end of the file, if it has been truncated
+pub fn read_top_level(file: &mut File) -> io::Result<Vec<BoxHeader>> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut reader = BufReader::new(file);
+    let mut pos = 0;
+    let mut headers = Vec::new();
+    while pos < len {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let kind: [u8; 4] = header[4..].try_into().unwrap();
+        let (start, size) = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            // A 64-bit size follows the type
+            1 => {
+                let mut large = [0; 8];
+                reader.read_exact(&mut large)?;
+                (pos + 16, u64::from_be_bytes(large).saturating_sub(16))
+            }
+            // The box runs to the end of the file
+            0 => (pos + 8, len - pos - 8),
+            size if size < 8 => {
+                return Err(invalid_data(format!(
+                    "invalid size for box \"{}\" at byte {}",
+                    kind_name(&kind),
+                    pos
+                )))
+            }
+            size => (pos + 8, size as u64 - 8),
+        };
+        let header = BoxHeader { kind, start, size };
+        let end = header.end();
+        headers.push(header);
+        match end {
+            Some(end) if end <= len => pos = end,
+            _ => break,
+        }
+    }
+    Ok(headers)
+}
+
+// Read the properties of an MP4 or QuickTime file from its movie box
+pub fn read_info(p: &Path) -> io::Result<MediaInfo> {
+    let mut file = File::open(p)?;
+    let headers = read_top_level(&mut file)?;
+    let brand = headers
+        .iter()
+        .find(|h| &h.kind == b"ftyp")
+        .map(|h| read_box(&mut file, h, 4))
+        .transpose()?;
+    let len = file.metadata()?.len();
+    let moov = match headers.iter().find(|h| &h.kind == b"moov") {
+        Some(moov) if moov.end().map_or(true, |end| end > len) => {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "movie box runs past the end of the file",
+            ))
+        }
+        Some(moov) if moov.size <= MAX_MOOV_SIZE => read_box(&mut file, moov, moov.size)?,
+        Some(_) => return Err(invalid_data("movie box is too large to read")),
+        None => return Err(invalid_data("no movie box found")),
+    };
+    let mut info = MediaInfo {
+        container: match brand.as_deref() {
+            Some(b"qt  ") => "QuickTime",
+            // Old QuickTime files have no file type box
+            None => "QuickTime",
+            _ => "MP4",
+        },
+        duration: None,
+        video: Vec::new(),
+        audio: Vec::new(),
+        subtitles: Vec::new(),
+    };
+    for (kind, data) in boxes(&moov) {
+        match &kind {
+            b"mvhd" => info.duration = read_duration(data),
+            b"trak" => read_track(data, &mut info),
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn read_box(file: &mut File, header: &BoxHeader, size: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(header.start))?;
+    let mut data = vec![0; size.min(header.size) as usize];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+// The child boxes of a box's data, as pairs of types and data.  Stops at the
+// first child which does not fit
+fn boxes(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    while data.len() >= 8 {
+        let kind: [u8; 4] = data[4..8].try_into().unwrap();
+        let (start, size) = match u32::from_be_bytes(data[..4].try_into().unwrap()) {
+            1 if data.len() >= 16 => (16, u64::from_be_bytes(data[8..16].try_into().unwrap())),
+            0 => (8, data.len() as u64),
+            size => (8, size as u64),
+        };
+        if size < start as u64 || size > data.len() as u64 {
+            break;
+        }
+        boxes.push((kind, &data[start..size as usize]));
+        data = &data[size as usize..];
+    }
+    boxes
+}
+
+// The data of the first child box with a type, following a path of types
+fn find<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    let (first, rest) = path.split_first()?;
+    let (_, child) = boxes(data).into_iter().find(|(kind, _)| kind == *first)?;
+    if rest.is_empty() {
+        Some(child)
+    } else {
+        find(child, rest)
+    }
+}
+
+// The movie header gives the duration in units of its timescale
+fn read_duration(mvhd: &[u8]) -> Option<f64> {
+    let (timescale, duration) = match *mvhd.first()? {
+        1 => (uint(mvhd.get(20..24)?), uint(mvhd.get(24..32)?)),
+        _ => (uint(mvhd.get(12..16)?), uint(mvhd.get(16..20)?)),
+    };
+    // A duration of all ones means it is unknown
+    if timescale == 0 || duration == u32::MAX as u64 || duration == u64::MAX {
+        return None;
+    }
+    Some(duration as f64 / timescale as f64)
+}
+
+fn read_track(trak: &[u8], info: &mut MediaInfo) {
+    let handler = find(trak, &[b"mdia", b"hdlr"]).and_then(|hdlr| hdlr.get(8..12));
+    let language = find(trak, &[b"mdia", b"mdhd"]).and_then(read_language);
+    let entry = find(trak, &[b"mdia", b"minf", b"stbl", b"stsd"])
+        .and_then(|stsd| stsd.get(8..))
+        .and_then(|entries| boxes(entries).into_iter().next());
+    let (codec, entry) = match entry {
+        Some((kind, entry)) => (kind_name(&kind), entry),
+        None => return,
+    };
+    match handler {
+        Some(b"vide") => {
+            // Sample entries give the coded size; fall back to the track header
+            let (mut width, mut height) = match entry.get(24..28) {
+                Some(size) => (uint(&size[..2]), uint(&size[2..])),
+                None => (0, 0),
+            };
+            if width == 0 || height == 0 {
+                if let Some((w, h)) = find(trak, &[b"tkhd"]).and_then(read_track_size) {
+                    width = w;
+                    height = h;
+                }
+            }
+            info.video.push(VideoTrack {
+                hdr: is_hdr(&codec, entry),
+                codec,
+                width,
+                height,
+            });
+        }
+        Some(b"soun") => info.audio.push(AudioTrack {
+            codec,
+            language,
+            channels: entry.get(16..18).map(uint).filter(|c| *c > 0),
+        }),
+        Some(b"sbtl") | Some(b"subt") | Some(b"text") | Some(b"clcp") => {
+            info.subtitles.push(SubtitleTrack {
+                codec,
+                language,
+                forced: false,
+            })
+        }
+        _ => {}
+    }
+}
+
+// The track header ends with the width and height, as 16.16 fixed point
+fn read_track_size(tkhd: &[u8]) -> Option<(u64, u64)> {
+    let size = tkhd.get(tkhd.len().checked_sub(8)?..)?;
+    Some((uint(&size[..4]) >> 16, uint(&size[4..]) >> 16))
+}
+
+// Whether a video sample entry is HDR: Dolby Vision, or with a colour box
+// giving an HDR transfer function (PQ or HLG)
+fn is_hdr(codec: &str, entry: &[u8]) -> bool {
+    if ["dvh1", "dvhe", "dvav", "dva1"].contains(&codec) {
+        return true;
+    }
+    // Child boxes follow the 78 bytes of a visual sample entry
+    let children = match entry.get(78..) {
+        Some(children) => boxes(children),
+        None => return false,
+    };
+    children.iter().any(|(kind, data)| match kind {
+        b"dvcC" | b"dvvC" => true,
+        b"colr" if matches!(data.get(..4), Some(b"nclx") | Some(b"nclc")) => {
+            data.get(6..8).map(uint).is_some_and(|t| t == 16 || t == 18)
+        }
+        _ => false,
+    })
+}
+
+// The media header gives the language as three five-bit letters (ISO 639-2).
+// QuickTime files may instead give a Macintosh language code, of which only
+// English (0) is common
+fn read_language(mdhd: &[u8]) -> Option<String> {
+    let offset = if *mdhd.first()? == 1 { 32 } else { 20 };
+    let code = uint(mdhd.get(offset..offset + 2)?) as u16;
+    if code < 0x400 {
+        return if code == 0 {
+            Some("eng".to_string())
+        } else {
+            None
+        };
+    }
+    let language: String = [10, 5, 0]
+        .iter()
+        .map(|shift| (((code >> shift) & 0x1F) as u8 + 0x60) as char)
+        .collect();
+    if language == "und" {
+        None
+    } else {
+        Some(language)
+    }
+}
+
+fn uint(data: &[u8]) -> u64 {
+    data.iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn kind_name(kind: &[u8; 4]) -> String {
+    String::from_utf8_lossy(kind).to_string()
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
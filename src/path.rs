@@ -1,6 +1,48 @@
 use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 
+use super::constants;
+
 pub fn get_extension_from_filename(filename: &Path) -> Option<&str> {
     filename.extension().and_then(OsStr::to_str)
 }
+
+pub fn is_media_file(filename: &Path) -> bool {
+    filename.is_file()
+        && get_extension_from_filename(filename)
+            .map(|ext| constants::MEDIA_TYPES.contains(&ext))
+            .unwrap_or(false)
+}
+
+// Whether a file is a subtitle, either text or image-based
+pub fn is_subtitle_file(filename: &Path) -> bool {
+    get_extension_from_filename(filename)
+        .map(|ext| {
+            constants::SUBTITLE_TYPES.contains(&ext)
+                || constants::BITMAP_SUBTITLE_TYPES.contains(&ext)
+        })
+        .unwrap_or(false)
+}
+
+pub fn is_bitmap_subtitle(filename: &Path) -> bool {
+    match get_extension_from_filename(filename) {
+        Some("sub") => is_vobsub_images(filename),
+        Some(ext) => constants::BITMAP_SUBTITLE_TYPES.contains(&ext),
+        None => false,
+    }
+}
+
+// Whether a .sub file holds the images of a VobSub pair, rather than MicroDVD
+// text: either it has a matching .idx file, or it is an MPEG program stream
+pub fn is_vobsub_images(filename: &Path) -> bool {
+    if filename.with_extension("idx").exists() {
+        return true;
+    }
+    let mut magic = [0; 4];
+    File::open(filename)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok()
+        && magic == [0x00, 0x00, 0x01, 0xBA]
+}
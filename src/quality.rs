@@ -0,0 +1,236 @@
+// This file provides a breakdown of films and episodes by video quality
+// (resolution, and whether they are HDR), read from their containers, to help
+// decide what to upgrade
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use colored::*;
+
+use super::dir::{self, MediaType};
+use super::junk;
+use super::media::{self, VideoTrack};
+use super::path;
+
+// Video quality by resolution, from lowest to highest.  Whether video is HDR
+// is kept separately, as it may be at any resolution
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Quality {
+    // The container could not be read
+    Unknown,
+    Sd,
+    Hd720,
+    Hd1080,
+    Uhd4k,
+}
+
+const QUALITIES: [Quality; 5] = [
+    Quality::Unknown,
+    Quality::Sd,
+    Quality::Hd720,
+    Quality::Hd1080,
+    Quality::Uhd4k,
+];
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Quality::Unknown => "Unknown",
+            Quality::Sd => "SD",
+            Quality::Hd720 => "720p",
+            Quality::Hd1080 => "1080p",
+            Quality::Uhd4k => "4K",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Quality {
+    // Films are often cropped to a wider aspect ratio than 16:9 (e.g.,
+    // 1920x800), so either dimension is enough to reach a resolution
+    fn of(video: &VideoTrack) -> Self {
+        if video.width >= 3200 || video.height >= 1800 {
+            Quality::Uhd4k
+        } else if video.width >= 1600 || video.height >= 900 {
+            Quality::Hd1080
+        } else if video.width >= 1200 || video.height >= 650 {
+            Quality::Hd720
+        } else {
+            Quality::Sd
+        }
+    }
+
+    fn coloured(&self) -> ColoredString {
+        let s = format!("[{}]", self);
+        match self {
+            Quality::Unknown | Quality::Sd => s.red(),
+            Quality::Hd720 => s.yellow(),
+            _ => s.green(),
+        }
+    }
+}
+
+// A film or episode, with its quality
+struct Item {
+    name: String,
+    quality: Quality,
+    hdr: bool,
+    // The resolution and codec, or why the file could not be read
+    detail: String,
+}
+
+// How many items there are of each quality, and how many of them are HDR
+#[derive(Default)]
+struct Breakdown {
+    counts: BTreeMap<Quality, usize>,
+    hdr: usize,
+}
+
+impl Breakdown {
+    fn of(items: &[Item]) -> Self {
+        let mut breakdown = Breakdown::default();
+        for item in items {
+            *breakdown.counts.entry(item.quality).or_default() += 1;
+            if item.hdr {
+                breakdown.hdr += 1;
+            }
+        }
+        breakdown
+    }
+
+    fn add(&mut self, other: &Breakdown) {
+        for (quality, count) in &other.counts {
+            *self.counts.entry(*quality).or_default() += count;
+        }
+        self.hdr += other.hdr;
+    }
+
+    fn describe(&self) -> String {
+        let counts = QUALITIES
+            .iter()
+            // Only mention files we could not read if there are any
+            .filter(|q| **q != Quality::Unknown || self.counts.contains_key(q))
+            .map(|q| format!("{} {}", q, self.counts.get(q).copied().unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{} (HDR {})", counts, self.hdr)
+    }
+}
+
+// Report the quality of every film and episode, with counts per library and
+// per series.  Items are listed lowest quality first
+pub fn show_quality_breakdown(dirname: &Path, media_type: &MediaType) {
+    for library in media_type.libraries() {
+        let mut library_dir = dirname.to_path_buf();
+        library_dir.push(library.as_str());
+        match library {
+            MediaType::Film => {
+                let items = read_items(&library_dir);
+                println!(
+                    "{}: {}",
+                    library.as_str().blue().bold(),
+                    Breakdown::of(&items).describe()
+                );
+                print_items(&items, 1);
+            }
+            MediaType::Series => {
+                let mut series: Vec<_> = fs::read_dir(&library_dir)
+                    .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", library_dir))
+                    .map(|e| e.expect("Cannot retreive file information").path())
+                    .filter(|p| p.is_dir())
+                    .map(|p| {
+                        let name = p.file_name().unwrap().to_string_lossy().to_string();
+                        (name, read_items(&p))
+                    })
+                    .filter(|(_, items)| !items.is_empty())
+                    .collect();
+                // Series with the lowest-quality episodes come first
+                series.sort_by(|(a_name, a), (b_name, b)| {
+                    (a[0].quality, a[0].hdr, a_name).cmp(&(b[0].quality, b[0].hdr, b_name))
+                });
+                let mut library_breakdown = Breakdown::default();
+                for (_, items) in &series {
+                    library_breakdown.add(&Breakdown::of(items));
+                }
+                println!(
+                    "{}: {}",
+                    library.as_str().blue().bold(),
+                    library_breakdown.describe()
+                );
+                for (name, items) in &series {
+                    println!(
+                        "\t{}: {}",
+                        name.blue().bold(),
+                        Breakdown::of(items).describe()
+                    );
+                    print_items(items, 2);
+                }
+            }
+            MediaType::Unknown => unreachable!(),
+        }
+    }
+}
+
+fn print_items(items: &[Item], indent: usize) {
+    let indent = "\t".repeat(indent);
+    for item in items {
+        let hdr = if item.hdr {
+            format!(" {}", "[HDR]".green())
+        } else {
+            String::new()
+        };
+        println!(
+            "{}{}{} {} {}",
+            indent,
+            item.quality.coloured(),
+            hdr,
+            item.name,
+            format!("({})", item.detail).italic()
+        );
+    }
+}
+
+// The quality of every media file under a directory, lowest first.  Names are
+// relative to the directory
+fn read_items(dir: &Path) -> Vec<Item> {
+    let mut items: Vec<Item> = dir::list_files(dir)
+        .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", dir))
+        .into_iter()
+        .filter(|p| path::is_media_file(p) && junk::junk_category(p).is_none())
+        .map(|p| {
+            let name = p.strip_prefix(dir).unwrap_or(&p).display().to_string();
+            let (quality, hdr, detail) = match media::read_media_info(&p) {
+                Ok(info) => {
+                    // Use the largest video track, if there are several
+                    match info
+                        .video
+                        .iter()
+                        .max_by_key(|v| v.width.saturating_mul(v.height))
+                    {
+                        Some(video) => (
+                            Quality::of(video),
+                            video.hdr,
+                            format!(
+                                "{}x{} {}",
+                                video.width,
+                                video.height,
+                                media::codec_name(&video.codec)
+                            ),
+                        ),
+                        None => (Quality::Unknown, false, "no video track".to_string()),
+                    }
+                }
+                Err(e) => (Quality::Unknown, false, e.to_string()),
+            };
+            Item {
+                name,
+                quality,
+                hdr,
+                detail,
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| (a.quality, a.hdr, &a.name).cmp(&(b.quality, b.hdr, &b.name)));
+    items
+}
@@ -47,13 +47,12 @@ pub fn check_consecutive_seasons(dirname: &Path) {
                     }
                 })
                 .collect();
-            // Check if these are consecutive
-            let max_se_num = season_numbers.iter().max().unwrap_or_else(|| {
-                panic!(
-                    "Cannot determine maximum season number from seasons {:?}",
-                    contents
-                )
-            });
+            // Check if these are consecutive.  Series without any seasons
+            // are reported by the structure check instead
+            let max_se_num = match season_numbers.iter().max() {
+                Some(max_se_num) => max_se_num,
+                None => continue,
+            };
             for i in 1..=*max_se_num {
                 if !season_numbers.contains(&i) {
                     if let Some(v) = missing_seasons_map.get_mut(series_name_outer) {
@@ -0,0 +1,144 @@
+// This file provides retiming of subtitle files: moving every cue by a fixed
+// offset, or stretching them from one framerate to another
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use colored::*;
+
+use super::cue::{self, SubtitleFormat};
+use super::encoding;
+use super::subtitles;
+
+// Shift the subtitle files at the given paths.  Times are first scaled from
+// the framerate the subtitles were timed for to the framerate of the video,
+// then moved by the offset.  Files are changed in place (keeping a backup),
+// unless an output file is given
+pub fn shift_subtitles(
+    paths: &[PathBuf],
+    offset: Option<&str>,
+    fps: Option<&str>,
+    output: Option<&Path>,
+) {
+    if offset.is_none() && fps.is_none() {
+        eprintln!("[ERROR] Nothing to do: use --offset, --fps, or both");
+        process::exit(1);
+    }
+    let offset = offset.map_or(0, |offset| {
+        parse_offset(offset).unwrap_or_else(|| {
+            eprintln!(
+                "[ERROR] Cannot read offset \"{}\"; expected seconds (e.g., -1.5) or a timestamp (e.g., 00:00:01,500)",
+                offset
+            );
+            process::exit(1);
+        })
+    });
+    let ratio = fps.map_or(1.0, |fps| {
+        parse_fps(fps).unwrap_or_else(|| {
+            eprintln!(
+                "[ERROR] Cannot read framerates \"{}\"; expected FROM:TO (e.g., 25:23.976)",
+                fps
+            );
+            process::exit(1);
+        })
+    });
+
+    let subs = subtitles::find_subtitles(paths);
+    if output.is_some() && subs.len() != 1 {
+        eprintln!("[ERROR] --output can only be used with a single subtitle file");
+        process::exit(1);
+    }
+    for sub in subs {
+        let format = match SubtitleFormat::from_path(&sub) {
+            Some(format) => format,
+            None => continue,
+        };
+        let (contents, original_encoding) = match encoding::read_to_string(&sub) {
+            Ok(read) => read,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", sub, e);
+                continue;
+            }
+        };
+        // Times which would be moved before the start of the video
+        let clamped = Cell::new(0);
+        let retimed = format.retime(&contents, &|ms| {
+            let ms = (ms as f64 * ratio).round() as i64 + offset;
+            if ms < 0 {
+                clamped.set(clamped.get() + 1);
+            }
+            ms.max(0)
+        });
+        // Keep the file in the encoding it was in
+        let retimed = encoding::encode(&retimed, original_encoding);
+        let written = match output {
+            Some(output) if output.exists() => {
+                eprintln!("[ERROR] Cannot write {:?}: file already exists", output);
+                continue;
+            }
+            Some(output) => fs::write(output, retimed).map(|_| {
+                format!(
+                    "{}{}",
+                    "Shifted into ".italic(),
+                    output.display().to_string().bold()
+                )
+            }),
+            None => encoding::write_with_backup(&sub, &retimed).map(|backup| {
+                format!(
+                    "{}{}",
+                    "Shifted in place; original kept as ".italic(),
+                    backup.display()
+                )
+            }),
+        };
+        match written {
+            Ok(message) => {
+                println!("{}", sub.display().to_string().bold());
+                println!("\t{}", message);
+                if clamped.get() > 0 {
+                    println!(
+                        "\t{}",
+                        format!(
+                            "{} times would have been before the start, and were set to 00:00:00",
+                            clamped.get()
+                        )
+                        .yellow()
+                    );
+                }
+            }
+            Err(e) => eprintln!("[ERROR] Cannot shift {:?}: {}", sub, e),
+        }
+    }
+}
+
+// Read an offset in milliseconds, given either in seconds ("2.5", "-1") or as
+// a timestamp ("00:00:02,500", "-01:30.000")
+fn parse_offset(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (sign, magnitude) = match s.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let ms = if magnitude.contains(':') {
+        cue::parse_timestamp(magnitude, true)?
+    } else {
+        let seconds: f64 = magnitude.parse().ok()?;
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+        (seconds * 1000.0).round() as i64
+    };
+    Some(sign * ms)
+}
+
+// Read a pair of framerates ("25:23.976") into the ratio to scale times by
+fn parse_fps(s: &str) -> Option<f64> {
+    let (from, to) = s.split_once(':')?;
+    let from: f64 = from.trim().parse().ok()?;
+    let to: f64 = to.trim().parse().ok()?;
+    if !(from > 0.0 && to > 0.0 && from.is_finite() && to.is_finite()) {
+        return None;
+    }
+    Some(from / to)
+}
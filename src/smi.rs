@@ -0,0 +1,113 @@
+// This file provides a parser and writer for SAMI (.smi) subtitle files.
+// SAMI is HTML-like: each "<SYNC Start=ms>" tag shows the text which follows
+// it until the next one, and a sync with no text clears the screen
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::cue::{self, Cue, Problem};
+
+lazy_static! {
+    static ref SYNC_RE: Regex = Regex::new(
+        r#"(?i)(?P<pre><sync[^>]*?\bstart\s*=\s*["']?)(?P<start>\d+)(?P<post>["']?[^>]*>)"#
+    )
+    .unwrap();
+    static ref P_RE: Regex = Regex::new(r"(?i)</?p\b[^>]*>").unwrap();
+    static ref BR_RE: Regex = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    static ref END_RE: Regex = Regex::new(r"(?i)</(sync|body|sami)>").unwrap();
+}
+
+// How long the final cue is shown for, when nothing clears it
+const FINAL_CUE_MS: i64 = 2000;
+
+// Parse a SAMI file into its cues, along with any problems found in it.  Text
+// is split into lines at "<br>" tags, and keeps any other markup
+pub fn parse(contents: &str) -> (Vec<Cue>, Vec<Problem>) {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut problems = Vec::new();
+    let syncs: Vec<_> = SYNC_RE.captures_iter(contents).collect();
+    if syncs.is_empty() {
+        problems.push(Problem::new(1, "No <SYNC> tags found"));
+        return (cues, problems);
+    }
+    for (i, caps) in syncs.iter().enumerate() {
+        let tag = caps.get(0).unwrap();
+        let line_num = contents[..tag.start()].lines().count().max(1);
+        let start: i64 = caps["start"].parse().unwrap_or(0);
+        if let Some(prev) = cues.last_mut() {
+            if prev.end < 0 {
+                prev.end = start;
+            }
+        }
+        let body_end = syncs
+            .get(i + 1)
+            .map(|c| c.get(0).unwrap().start())
+            .unwrap_or(contents.len());
+        let body = &contents[tag.end()..body_end];
+        let body = END_RE.split(body).next().unwrap_or("");
+        let body = P_RE.replace_all(body, "");
+        let text: Vec<String> = BR_RE
+            .split(&body)
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        if text
+            .iter()
+            .all(|l| l.is_empty() || l.eq_ignore_ascii_case("&nbsp;"))
+        {
+            continue;
+        }
+        cues.push(Cue {
+            start,
+            // Filled in from the next sync
+            end: -1,
+            line: line_num,
+            text,
+            settings: String::new(),
+            style: String::new(),
+        });
+    }
+    if let Some(last) = cues.last_mut() {
+        if last.end < 0 {
+            problems.push(Problem::new(
+                last.line,
+                "Final cue is never cleared; assuming it lasts two seconds",
+            ));
+            last.end = last.start + FINAL_CUE_MS;
+        }
+    }
+    problems.extend(cue::check_timing(&cues));
+    (cues, problems)
+}
+
+// Write cues as a SAMI file, marking the text as being in the given language.
+// Cue text should already use SAMI markup
+pub fn write(cues: &[Cue], lang: &str) -> String {
+    let mut out = String::new();
+    out.push_str("<SAMI>\n<HEAD>\n<STYLE TYPE=\"text/css\">\n<!--\n");
+    out.push_str("P { font-family: Arial; text-align: center; }\n");
+    out.push_str(&format!(".SUBTTL {{ Name: Subtitles; lang: {}; }}\n", lang));
+    out.push_str("-->\n</STYLE>\n</HEAD>\n<BODY>\n");
+    for (i, cue) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "<SYNC Start={}><P Class=SUBTTL>{}\n",
+            cue.start,
+            cue.text.join("<br>")
+        ));
+        // Clear the screen, unless the next cue replaces this one
+        if cues.get(i + 1).map_or(true, |next| next.start > cue.end) {
+            out.push_str(&format!("<SYNC Start={}><P Class=SUBTTL>&nbsp;\n", cue.end));
+        }
+    }
+    out.push_str("</BODY>\n</SAMI>\n");
+    out
+}
+
+// Rewrite the time of every sync with `f`
+pub fn retime(contents: &str, f: &dyn Fn(i64) -> i64) -> String {
+    SYNC_RE
+        .replace_all(contents, |caps: &regex::Captures| {
+            let start: i64 = caps["start"].parse().unwrap_or(0);
+            format!("{}{}{}", &caps["pre"], f(start).max(0), &caps["post"])
+        })
+        .to_string()
+}
@@ -0,0 +1,126 @@
+// This file provides a parser for SubRip (.srt) subtitle files
+use super::cue::{self, Cue, Problem};
+
+// Parse an SRT file into its cues, along with any problems found in it.  We
+// carry on past problems where we can, so that every problem in a file can be
+// reported at once
+pub fn parse(contents: &str) -> (Vec<Cue>, Vec<Problem>) {
+    let lines = cue::lines(contents);
+    let mut cues = Vec::new();
+    let mut problems = Vec::new();
+    if lines.iter().all(|l| l.trim().is_empty()) {
+        problems.push(Problem::new(1, "File is empty"));
+        return (cues, problems);
+    }
+
+    let mut expected_num = 1;
+    for (start, block) in cue::blocks(&lines) {
+        let mut block = block.iter().enumerate().map(|(i, l)| (start + i, *l));
+        let (mut line_num, mut line) = block.next().unwrap();
+
+        // Cue number
+        if line.contains("-->") {
+            problems.push(Problem::new(line_num, "Missing cue number"));
+        } else {
+            match line.trim().parse::<usize>() {
+                Ok(num) => {
+                    if num != expected_num {
+                        problems.push(Problem::new(
+                            line_num,
+                            format!(
+                                "Cue number {} is out of sequence (expected {})",
+                                num, expected_num
+                            ),
+                        ));
+                    }
+                    expected_num = num + 1;
+                }
+                Err(_) => {
+                    problems.push(Problem::new(
+                        line_num,
+                        format!("Expected a cue number, found \"{}\"", line.trim()),
+                    ));
+                    // Likely a stray line of text from the previous cue
+                    continue;
+                }
+            }
+            match block.next() {
+                Some((n, l)) => {
+                    line_num = n;
+                    line = l;
+                }
+                None => {
+                    problems.push(Problem::new(line_num, "Cue has no timestamps"));
+                    continue;
+                }
+            }
+        }
+
+        // Timestamps
+        let timing = cue::split_timing_line(line).and_then(|(start, end, _)| {
+            Some((
+                start,
+                end,
+                cue::parse_timestamp(start, false)?,
+                cue::parse_timestamp(end, false)?,
+            ))
+        });
+        let (start_ms, end_ms) = match timing {
+            Some((start, end, start_ms, end_ms)) => {
+                if start.contains('.') || end.contains('.') {
+                    problems.push(Problem::new(
+                        line_num,
+                        "Timestamps should use ',' rather than '.' before the milliseconds",
+                    ));
+                }
+                (start_ms, end_ms)
+            }
+            None => {
+                problems.push(Problem::new(
+                    line_num,
+                    format!("Malformed timestamps \"{}\"", line.trim()),
+                ));
+                continue;
+            }
+        };
+
+        // Text
+        let text: Vec<String> = block.map(|(_, l)| l.to_string()).collect();
+        if text.is_empty() {
+            problems.push(Problem::new(line_num, "Cue has no text"));
+        }
+        cues.push(Cue {
+            start: start_ms,
+            end: end_ms,
+            line: line_num,
+            text,
+            settings: String::new(),
+            style: String::new(),
+        });
+    }
+    problems.extend(cue::check_timing(&cues));
+    (cues, problems)
+}
+
+// Write cues as an SRT file, numbering them from one.  Cue text should
+// already use SRT markup
+pub fn write(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, c) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            cue::format_timestamp(c.start, ','),
+            cue::format_timestamp(c.end, ','),
+            c.text.join("\n")
+        ));
+    }
+    out
+}
+
+// Rewrite the times of every cue with `f`
+pub fn retime(contents: &str, f: &dyn Fn(i64) -> i64) -> String {
+    cue::map_lines(contents, |line| {
+        cue::retime_timing_line(line, false, ',', f).unwrap_or_else(|| line.to_string())
+    })
+}
@@ -0,0 +1,273 @@
+// This file provides stripping of hearing-impaired annotations from subtitle
+// files: bracketed sound cues (e.g., "[door slams]") and speaker labels
+// (e.g., "JOHN: "), so that SDH subtitles can be used as plain ones.  Sound
+// cues in parentheses are only removed when they are a whole line or cue, as
+// parentheses are also used in dialogue
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use colored::*;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use super::ass;
+use super::cue::{Cue, SubtitleFormat};
+use super::encoding;
+use super::smi;
+use super::srt;
+use super::subtitles::{self, SubtitleName};
+use super::vtt;
+
+// Upper-case words which may start a line of dialogue with a colon, but are
+// not speakers
+const NOT_LABELS: [&str; 10] = [
+    "YES", "HEY", "NOTE", "OKAY", "STOP", "WAIT", "WARNING", "WELL", "WHAT", "WHY",
+];
+
+lazy_static! {
+    // A bracketed sound cue or description, which may run over several lines
+    static ref ANNOTATION_RE: Regex = Regex::new(r"\[[^\]]*\]").unwrap();
+    // A line which is only a parenthesised sound cue, apart from any markup or
+    // dialogue dash (e.g., "- (laughs)")
+    static ref PAREN_LINE_RE: Regex = Regex::new(r"^(?P<prefix>(?:<[^>]*>|\{[^}]*\})*\s*(?:-\s*)?)\([^)]*\)(?P<suffix>(?:\s*(?:<[^>]*>|\{[^}]*\}))*)\s*$").unwrap();
+    // A cue (without markup) which is only a parenthesised sound cue, which may
+    // run over several lines
+    static ref PAREN_CUE_RE: Regex = Regex::new(r"^\s*(?:-\s*)?\([^)]*\)\s*$").unwrap();
+    // An upper-case speaker label at the start of a line, after any markup or
+    // dialogue dash (e.g., "- JOHN: ", "<i>MAN (ON TV): ")
+    static ref LABEL_RE: Regex = Regex::new(r"^(?P<prefix>(?:<[^>]*>|\{[^}]*\})*\s*(?:-\s*)?)(?P<label>[A-Z][A-Z0-9 .'#&-]*[A-Z0-9])(?:\s*\([^)]*\))?:(?:\s+|$)").unwrap();
+    static ref DASH_RE: Regex = Regex::new(r"^(?P<prefix>(?:<[^>]*>|\{[^}]*\})*)\s*-\s*").unwrap();
+    static ref MARKUP_RE: Regex = Regex::new(r"<[^>]*>|\{[^}]*\}").unwrap();
+    static ref SPACES_RE: Regex = Regex::new(r"[ \t]{2,}").unwrap();
+}
+
+// What was removed from a subtitle file
+#[derive(Default)]
+struct Stripped {
+    annotations: usize,
+    labels: usize,
+    dropped: usize,
+}
+
+// Strip hearing-impaired annotations from subtitle files, writing each
+// alongside the original as the plain (i.e., not SDH or CC) variant.  If the
+// original is not marked as SDH, it is renamed to be (after asking for
+// confirmation), so as not to be replaced
+pub fn strip_hi_subtitles(paths: &[PathBuf]) {
+    for sub in subtitles::find_subtitles(paths) {
+        let format = match SubtitleFormat::from_path(&sub) {
+            Some(format) => format,
+            None => continue,
+        };
+        let name = sub.file_name().unwrap().to_string_lossy().to_string();
+        let parsed = match SubtitleName::parse(&name) {
+            Some(parsed) => parsed,
+            None => {
+                eprintln!(
+                    "[ERROR] Cannot name the stripped copy of {:?}; use -C to find its correct name first",
+                    sub
+                );
+                continue;
+            }
+        };
+        let contents = match encoding::read_to_string(&sub) {
+            Ok((contents, _)) => contents,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", sub, e);
+                continue;
+            }
+        };
+        println!("{}", sub.display().to_string().bold());
+        let mut stripped = Stripped::default();
+        let output = match strip_contents(format, &contents, &parsed, &mut stripped) {
+            Ok(output) => output,
+            Err(problems) => {
+                println!(
+                    "\t{}",
+                    format!(
+                        "{} problems found while reading, so cues could be lost; nothing written.  See `filmls subtitles validate`",
+                        problems
+                    )
+                    .yellow()
+                );
+                continue;
+            }
+        };
+        if stripped.annotations == 0 && stripped.labels == 0 {
+            println!(
+                "\t{}",
+                "No sound cues or speaker labels found; nothing written".italic()
+            );
+            continue;
+        }
+        let plain = SubtitleName {
+            sdh: false,
+            cc: false,
+            ..parsed
+        };
+        let plain_path = sub.with_file_name(plain.file_name(format.extension()));
+        let sdh_path = if plain_path == sub {
+            let sdh = SubtitleName { sdh: true, ..plain };
+            let sdh_path = sub.with_file_name(sdh.file_name(format.extension()));
+            if !confirm(&format!(
+                "\tRename the original to {} so the stripped copy can take its name? [y/N] ",
+                sdh_path.file_name().unwrap().to_string_lossy()
+            )) {
+                println!("\t{}", "Original left as it is; nothing written".italic());
+                continue;
+            }
+            if let Err(e) = rename(&sub, &sdh_path) {
+                eprintln!("[ERROR] Cannot rename {:?} to {:?}: {}", sub, sdh_path, e);
+                continue;
+            }
+            Some(sdh_path)
+        } else if plain_path.exists() {
+            eprintln!("[ERROR] Cannot write {:?}: file already exists", plain_path);
+            continue;
+        } else {
+            None
+        };
+        if let Err(e) = fs::write(&plain_path, output) {
+            eprintln!("[ERROR] Cannot write {:?}: {}", plain_path, e);
+            continue;
+        }
+
+        for (count, what) in [
+            (stripped.annotations, "sound cues removed"),
+            (stripped.labels, "speaker labels removed"),
+            (stripped.dropped, "cues left empty and dropped"),
+        ] {
+            if count > 0 {
+                println!("\t{} {}", count.to_string().bold(), what.italic());
+            }
+        }
+        if let Some(sdh_path) = sdh_path {
+            println!(
+                "\t{}{}",
+                "Original renamed to ".italic(),
+                sdh_path.file_name().unwrap().to_string_lossy()
+            );
+        }
+        println!(
+            "\t{}{}",
+            "Written to ".italic(),
+            plain_path.file_name().unwrap().to_string_lossy().green()
+        );
+    }
+}
+
+// Ask a yes or no question, taking anything but yes as no
+fn confirm(question: &str) -> bool {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+// Rename a file, refusing to replace an existing one
+fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "file already exists",
+        ));
+    }
+    fs::rename(from, to)
+}
+
+// Strip a whole file.  ASS and SSA files are rewritten in place, so as to keep
+// their styles; the others are parsed and written out again, which renumbers
+// their cues.  Anything the parser cannot read would be left out of the
+// output, so files with problems are not stripped; the number of problems is
+// returned instead
+fn strip_contents(
+    format: SubtitleFormat,
+    contents: &str,
+    name: &SubtitleName,
+    stripped: &mut Stripped,
+) -> Result<String, usize> {
+    if let SubtitleFormat::Ass | SubtitleFormat::Ssa = format {
+        return Ok(ass::map_text(contents, |text| {
+            let lines: Vec<String> = text.split("\\N").map(String::from).collect();
+            let lines = strip_lines(&lines, stripped);
+            if lines.is_empty() {
+                stripped.dropped += 1;
+                None
+            } else {
+                Some(lines.join("\\N"))
+            }
+        }));
+    }
+    let (cues, problems) = format.parse(contents);
+    if !problems.is_empty() {
+        return Err(problems.len());
+    }
+    let cues: Vec<Cue> = cues
+        .into_iter()
+        .filter_map(|mut cue| {
+            cue.text = strip_lines(&cue.text, stripped);
+            if cue.text.is_empty() {
+                stripped.dropped += 1;
+                None
+            } else {
+                Some(cue)
+            }
+        })
+        .collect();
+    Ok(match format {
+        SubtitleFormat::Vtt => vtt::write(&cues),
+        SubtitleFormat::Smi => smi::write(&cues, &name.locale),
+        _ => srt::write(&cues),
+    })
+}
+
+// Strip the lines of a cue, returning the lines left with any text in them
+fn strip_lines(lines: &[String], stripped: &mut Stripped) -> Vec<String> {
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| match LABEL_RE.captures(line) {
+            Some(caps) if is_speaker(&caps["label"]) => {
+                stripped.labels += 1;
+                LABEL_RE.replace(line, "${prefix}").to_string()
+            }
+            _ => line.clone(),
+        })
+        .collect();
+    // Sound cues may be split over lines, so remove them from the whole cue
+    let text = lines.join("\n");
+    if PAREN_CUE_RE.is_match(&MARKUP_RE.replace_all(&text, "")) {
+        stripped.annotations += 1;
+        return Vec::new();
+    }
+    let text = ANNOTATION_RE.replace_all(&text, |_: &Captures| {
+        stripped.annotations += 1;
+        String::new()
+    });
+    let mut lines: Vec<String> = text
+        .split('\n')
+        .map(|line| {
+            if PAREN_LINE_RE.is_match(line) {
+                stripped.annotations += 1;
+            }
+            PAREN_LINE_RE.replace(line, "${prefix}${suffix}")
+        })
+        .map(|line| SPACES_RE.replace_all(&line, " ").trim().to_string())
+        .filter(|line| {
+            let bare = MARKUP_RE.replace_all(line, "");
+            !bare.trim().trim_start_matches('-').trim().is_empty()
+        })
+        .collect();
+    // A dialogue dash is only needed when there is more than one speaker
+    if let [line] = lines.as_mut_slice() {
+        *line = DASH_RE.replace(line, "${prefix}").to_string();
+    }
+    lines
+}
+
+// Whether an upper-case label is a speaker, rather than a short word such as
+// "OK" or a common exclamation
+fn is_speaker(label: &str) -> bool {
+    label.len() > 2 && !NOT_LABELS.contains(&label)
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use super::constants;
+use super::diagnostics::{self, Diagnostic};
+use super::dir::MediaType;
+use super::episodes;
+use super::manifest::{self, Manifest};
+use super::path;
+
+// Problems with how a series directory is laid out
+enum Finding {
+    // The series directory has nothing in it at all
+    Empty,
+    // The series directory has contents, but no season directories
+    NoSeasons,
+    // A media file which sits directly in the series directory, rather than
+    // in a season directory.  If we can parse the episode's season, we can
+    // suggest where it should go
+    LooseEpisode(String, Option<String>),
+    // A directory in the series which is not a season directory
+    UnexpectedFolder(String),
+    // A media file in a season directory whose name we do not understand
+    UnrecognisedEpisode(String, String),
+}
+
+impl Finding {
+    fn describe(&self) -> String {
+        match self {
+            Finding::Empty => "Series directory is empty".to_string(),
+            Finding::NoSeasons => "Series has no season directories".to_string(),
+            Finding::LooseEpisode(ep, Some(season_dir)) => format!(
+                "Episode outside of season directory: {} (expected in \"{}\")",
+                ep, season_dir
+            ),
+            Finding::LooseEpisode(ep, None) => {
+                format!("Episode outside of season directory: {}", ep)
+            }
+            Finding::UnexpectedFolder(d) => format!("Unexpected directory: {}", d),
+            Finding::UnrecognisedEpisode(season_dir, ep) => {
+                format!("Unrecognised episode name in \"{}\": {}", season_dir, ep)
+            }
+        }
+    }
+
+    fn diagnose(&self) -> Option<Diagnostic> {
+        match self {
+            Finding::UnexpectedFolder(d) => diagnostics::diagnose_season(d),
+            Finding::UnrecognisedEpisode(_, ep) => diagnostics::diagnose_episode(ep),
+            _ => None,
+        }
+    }
+}
+
+// Plex allows specials to be stored in their own directory
+const SPECIALS_DIR_NAME: &str = "Specials";
+
+pub fn check_series_structure(dirname: &Path) {
+    let mut series_dir = dirname.to_path_buf();
+    series_dir.push(MediaType::Series.as_str());
+    // Get series available
+    let mut series: Vec<_> = fs::read_dir(&series_dir)
+        .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", series_dir))
+        .map(|e| e.expect("Cannot retreive file information").path())
+        .collect();
+    series.sort();
+    let manifest = Manifest::load(dirname);
+    // Search through series
+    for path in series {
+        let series_name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if series_name == manifest::MANIFEST_FILE_NAME {
+            continue;
+        }
+        if !path.is_dir() {
+            println!(
+                "{}{}{}",
+                "File ".italic(),
+                series_name.bold(),
+                " is not inside a series directory".italic()
+            );
+            continue;
+        }
+        let absolute = manifest.get(&path).is_some_and(|m| m.absolute);
+        let findings = series_findings(&path, absolute);
+        if findings.is_empty() {
+            continue;
+        }
+        println!("{}", series_name.blue().bold());
+        for finding in findings {
+            println!("\t{}", finding.describe().blue());
+            if let Some(diagnostic) = finding.diagnose() {
+                diagnostic.print(2);
+            }
+        }
+    }
+}
+
+fn series_findings(series_path: &Path, absolute: bool) -> Vec<Finding> {
+    let mut contents: Vec<_> = fs::read_dir(series_path)
+        .expect("Cannot read directory")
+        .map(|e| e.expect("Cannot retreive file information").path())
+        .collect();
+    contents.sort();
+    if contents.is_empty() {
+        return vec![Finding::Empty];
+    }
+
+    // Series with absolute numbering do not need season directories
+    let absolute_eps: Vec<_> = if absolute {
+        episodes::absolute_episodes(series_path)
+            .into_iter()
+            .map(|e| e.path)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut findings = Vec::new();
+    let mut has_seasons = absolute;
+    for p in contents {
+        let name = p.file_name().unwrap().to_str().unwrap().to_string();
+        if p.is_dir() {
+            if constants::SEASON_RE.is_match(&name) || name == SPECIALS_DIR_NAME {
+                has_seasons = true;
+                findings.extend(unrecognised_episodes(&p, &absolute_eps));
+            } else {
+                findings.push(Finding::UnexpectedFolder(name));
+            }
+        } else if path::is_media_file(&p) && !absolute_eps.contains(&p) {
+            let season_dir = constants::EP_RE.captures(&name).map(|caps| {
+                let season_num = caps["snum"].parse::<isize>().unwrap();
+                format!("Season {:02}", season_num)
+            });
+            findings.push(Finding::LooseEpisode(name, season_dir));
+        }
+    }
+    if !has_seasons {
+        findings.insert(0, Finding::NoSeasons);
+    }
+    findings
+}
+
+// Media files in a season directory which are not named like an episode
+fn unrecognised_episodes(season_path: &Path, absolute_eps: &[PathBuf]) -> Vec<Finding> {
+    let season_dir = season_path.file_name().unwrap().to_str().unwrap();
+    let mut episodes: Vec<_> = fs::read_dir(season_path)
+        .expect("Cannot read directory")
+        .map(|e| e.expect("Cannot retreive file information").path())
+        .filter(|p| path::is_media_file(p) && !absolute_eps.contains(p))
+        .collect();
+    episodes.sort();
+    episodes
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+        .filter(|ep| !constants::EP_RE.is_match(ep))
+        .map(|ep| Finding::UnrecognisedEpisode(season_dir.to_string(), ep))
+        .collect()
+}
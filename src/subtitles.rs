@@ -1,46 +1,156 @@
 use super::constants;
-use super::dir::MediaType;
+use super::diagnostics;
+use super::dir::{self, MediaType};
+use super::langdetect;
+use super::locale;
 use super::path;
 use colored::*;
 use regex::Regex;
-use std::path::Path;
-use std::{fs, io, process};
-
-pub fn list_erroneous_subtitles(dirname: &Path, media_type: &MediaType) {
-    match media_type {
-        MediaType::Film => {
-            let mut films_dir = dirname.to_path_buf();
-            films_dir.push(media_type.as_str());
-            for sub in list_subtitles(&films_dir) {
-                // TODO: warn if subtitle base name does not match film
-                if !check_subtitle_format(&sub, &constants::SUB_RE) {
-                    println!(
-                        "{}{}{}",
-                        "Subtitle file ".italic(),
-                        sub.bold(),
-                        " is incorrectly formatted".italic()
-                    );
-                }
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+// The parts of a correctly-formatted subtitle file name
+pub struct SubtitleName {
+    pub fname: String,
+    pub locale: String,
+    pub forced: bool,
+    pub sdh: bool,
+    pub cc: bool,
+}
+
+impl SubtitleName {
+    pub fn parse(name: &str) -> Option<Self> {
+        let caps = constants::SUB_RE.captures(name)?;
+        let flags: Vec<&str> = caps["flags"].split('.').collect();
+        Some(SubtitleName {
+            fname: caps["fname"].to_string(),
+            locale: caps["locale"].to_string(),
+            forced: flags.contains(&"forced"),
+            sdh: flags.contains(&"sdh"),
+            cc: flags.contains(&"cc"),
+        })
+    }
+
+    pub fn language(&self) -> &str {
+        self.locale.split('-').next().unwrap_or(&self.locale)
+    }
+
+    // The file name for this subtitle, with the given extension
+    pub fn file_name(&self, ext: &str) -> String {
+        let flags: String = [
+            (self.forced, ".forced"),
+            (self.sdh, ".sdh"),
+            (self.cc, ".cc"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, flag)| *flag)
+        .collect();
+        format!("{}.{}{}.{}", self.fname, self.locale, flags, ext)
+    }
+
+    // A human-readable description of the subtitle (e.g., "English (forced)")
+    pub fn describe(&self) -> String {
+        let lang = locale::language_name(self.language()).unwrap_or(&self.locale);
+        let mut desc = match self.locale.split_once('-') {
+            Some((_, region)) => format!("{} ({})", lang, region),
+            None => lang.to_string(),
+        };
+        let flags: Vec<&str> = [(self.forced, "forced"), (self.sdh, "SDH"), (self.cc, "CC")]
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, flag)| *flag)
+            .collect();
+        if !flags.is_empty() {
+            desc = format!("{} ({})", desc, flags.join(", "));
+        }
+        desc
+    }
+}
+
+// Counts of the problems found with the subtitle files in a library
+#[derive(Default)]
+struct SubtitleSummary {
+    checked: usize,
+    with_problems: usize,
+    incorrectly_formatted: usize,
+    unconventionally_named: usize,
+    mismatched: usize,
+    orphaned: usize,
+    unpaired: usize,
+}
+
+impl SubtitleSummary {
+    fn print(&self) {
+        if self.with_problems == 0 {
+            println!(
+                "\t{}",
+                format!("{} subtitle files checked; no problems found", self.checked).italic()
+            );
+            return;
+        }
+        println!(
+            "\t{}{}{}",
+            self.with_problems.to_string().bold(),
+            " of ".italic(),
+            format!("{} subtitle files have problems:", self.checked).italic()
+        );
+        for (count, problem) in [
+            (self.incorrectly_formatted, "incorrectly formatted"),
+            (self.unconventionally_named, "unconventionally named"),
+            (self.mismatched, "do not match their media file"),
+            (self.orphaned, "have no matching media file"),
+            (self.unpaired, "are missing the other half of a VobSub pair"),
+        ] {
+            if count > 0 {
+                println!("\t\t{} {}", count.to_string().bold(), problem.italic());
             }
         }
-        MediaType::Series => {
-            let mut series_dir = dirname.to_path_buf();
-            series_dir.push(media_type.as_str());
+    }
+}
 
+// Report badly named subtitle files in each library, along with the name each
+// should have.  With `fix`, rename them to those names after asking for
+// confirmation
+pub fn list_erroneous_subtitles(dirname: &Path, media_type: &MediaType, fix: bool) {
+    let mut renames = Vec::new();
+    for library in media_type.libraries() {
+        println!("{}", library.as_str().blue().bold());
+        let mut summary = SubtitleSummary::default();
+        for sub in library_subtitles(dirname, &library) {
+            renames.extend(report_subtitle(&sub, &mut summary));
+        }
+        summary.print();
+    }
+    if fix {
+        rename_subtitles(&renames);
+    }
+}
+
+// The subtitle files in a library.  For series, only season directories are
+// searched
+fn library_subtitles(dirname: &Path, library: &MediaType) -> Vec<PathBuf> {
+    let mut library_dir = dirname.to_path_buf();
+    library_dir.push(library.as_str());
+    match library {
+        MediaType::Film => list_subtitles(&library_dir),
+        MediaType::Series => {
             // Exit gracefully if the series directory doesn't exist
             // Rather than panicking in the next step
-            if !series_dir.exists() {
-                eprintln!("No such file or directory: {:?}", series_dir);
+            if !library_dir.exists() {
+                eprintln!("No such file or directory: {:?}", library_dir);
                 process::exit(1);
             }
 
             // Get individual series from series directory
-            let series: Vec<_> = fs::read_dir(&series_dir)
-                .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", series_dir))
+            let series: Vec<_> = fs::read_dir(&library_dir)
+                .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", library_dir))
                 .map(|e| e.expect("Cannot retreive file information").path())
                 .collect();
 
             // Check subtitle format for each season for each series
+            let mut subs = Vec::new();
             for path in series {
                 if path.is_dir() {
                     for p in fs::read_dir(&path)
@@ -55,58 +165,318 @@ pub fn list_erroneous_subtitles(dirname: &Path, media_type: &MediaType) {
                             .to_str()
                             .unwrap();
                         if constants::SEASON_RE.is_match(d) {
-                            for sub in list_subtitles(p) {
-                                // TODO: warn if subtitle base name does not match film
-                                if !check_subtitle_format(&sub, &constants::SUB_RE) {
-                                    println!(
-                                        "{}{}{}",
-                                        "Subtitle file ".italic(),
-                                        sub.bold(),
-                                        " is incorrectly formatted".italic()
-                                    );
-                                }
-                            }
+                            subs.extend(list_subtitles(p));
                         }
                     }
                 }
             }
+            subs
         }
-        MediaType::Unknown | MediaType::Impossible => {
-            panic!("Unhandled media type");
+        MediaType::Unknown => unreachable!(),
+    }
+}
+
+// Subtitle files given on the command line, where directories are searched
+// recursively for subtitles
+pub fn find_subtitles(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut subs = Vec::new();
+    for p in paths {
+        if p.is_dir() {
+            subs.extend(list_subtitles(p));
+        } else if p.exists() {
+            subs.push(p.clone());
+        } else {
+            eprintln!("No such file or directory: {:?}", p);
+            process::exit(1);
         }
     }
+    subs
 }
 
-fn list_subtitles(dir: &Path) -> Vec<String> {
+fn list_subtitles(dir: &Path) -> Vec<PathBuf> {
     // First check that the path exists
     if !dir.exists() {
         eprintln!("No such file or directory: {:?}", dir);
         process::exit(1);
     }
 
-    // TODO: use walkdir or something (see jakewilliami/lsext)
-    fn recurse_files_count_if_media(dir: &Path, subs: &mut Vec<String>) -> io::Result<()> {
-        if dir.is_dir() {
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    recurse_files_count_if_media(&path, subs)?;
-                } else {
-                    let ext = path::get_extension_from_filename(&path);
-                    if ext.is_some() && constants::SUBTITLE_TYPES.contains(&ext.unwrap()) {
-                        subs.push(path.file_name().unwrap().to_str().unwrap().to_string());
-                    }
-                }
-            }
+    dir::list_files(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| path::is_subtitle_file(p))
+        .collect()
+}
+
+// Report a subtitle file if it is badly named, returning the renames it needs,
+// if we know them.  The .sub half of a VobSub pair is named the same as its
+// .idx, so is only reported (and renamed) along with it
+fn report_subtitle(sub: &Path, summary: &mut SubtitleSummary) -> Vec<(PathBuf, PathBuf)> {
+    let ext = path::get_extension_from_filename(sub);
+    if ext == Some("sub") && sub.with_extension("idx").exists() {
+        return Vec::new();
+    }
+    let name = sub.file_name().unwrap().to_str().unwrap();
+    summary.checked += 1;
+    // Both reports should be printed, so do not short-circuit
+    let reported = report_subtitle_format(name, summary)
+        | report_subtitle_media(sub, summary)
+        | report_vobsub_pair(sub, summary);
+    if !reported {
+        return Vec::new();
+    }
+    summary.with_problems += 1;
+    let proposed = match proposed_name(sub).filter(|p| p != name) {
+        Some(proposed) => sub.with_file_name(proposed),
+        None => return Vec::new(),
+    };
+    println!(
+        "\t\t{}{}",
+        "Proposed name: ".italic(),
+        proposed.file_name().unwrap().to_string_lossy().green()
+    );
+    let mut renames = vec![(sub.to_path_buf(), proposed.clone())];
+    let images = sub.with_extension("sub");
+    if ext == Some("idx") && images.exists() {
+        renames.push((images, proposed.with_extension("sub")));
+    }
+    renames
+}
+
+// The name a subtitle file should have: the name of the media file it belongs
+// to, followed by its language and flags.  If we cannot tell which media file
+// it belongs to, the name is only corrected.  When the name has no language,
+// it is detected from the subtitle's text
+fn proposed_name(sub: &Path) -> Option<String> {
+    let name = sub.file_name()?.to_str()?;
+    let ext = path::get_extension_from_filename(sub)?.to_lowercase();
+    let mut sub_name = match diagnostics::diagnose_subtitle(name) {
+        Some(diagnostic) => match diagnostic.suggestion {
+            Some(suggestion) => SubtitleName::parse(&suggestion)?,
+            // Without a language in the name, detect it from the text.  The
+            // rest of the name is replaced by the media file's, if we find it
+            None => SubtitleName {
+                fname: sub.file_stem()?.to_str()?.to_string(),
+                locale: langdetect::detect_subtitle(sub)?.to_string(),
+                forced: false,
+                sdh: false,
+                cc: false,
+            },
+        },
+        None => SubtitleName::parse(name)?,
+    };
+    let media_names = sibling_media_names(sub);
+    if !media_names.contains(&sub_name.fname) {
+        let is_episode = constants::EP_RE.is_match(&format!("{}.ext", sub_name.fname));
+        // A lone video is the only thing a film's subtitles can be for
+        let lone_media = match media_names.as_slice() {
+            [media_name] if !is_episode => Some(media_name),
+            _ => None,
+        };
+        let media_name = closest_media_name(&sub_name.fname, &media_names).or(lone_media);
+        if let Some(media_name) = media_name {
+            sub_name.fname = media_name.clone();
         }
-        Ok(())
     }
-    let mut subs = Vec::new();
-    let _ = recurse_files_count_if_media(dir, &mut subs);
-    subs
+    Some(sub_name.file_name(&ext))
+}
+
+// Rename subtitle files, after asking for confirmation.  Existing files are
+// never overwritten
+fn rename_subtitles(renames: &[(PathBuf, PathBuf)]) {
+    if renames.is_empty() {
+        println!("{}", "No subtitle files to rename".italic());
+        return;
+    }
+    print!(
+        "Rename {} subtitle files as proposed? [y/N] ",
+        renames.len()
+    );
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err()
+        || !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    {
+        println!("{}", "No files renamed".italic());
+        return;
+    }
+    for (from, to) in renames {
+        if to.exists() {
+            eprintln!("[ERROR] Cannot rename {:?}: {:?} already exists", from, to);
+            continue;
+        }
+        match fs::rename(from, to) {
+            Ok(()) => println!(
+                "{}{}{}",
+                from.display().to_string().bold(),
+                " renamed to ".italic(),
+                to.file_name().unwrap().to_string_lossy().green()
+            ),
+            Err(e) => eprintln!("[ERROR] Cannot rename {:?}: {}", from, e),
+        }
+    }
+}
+
+// Print a subtitle file if it is incorrectly formatted, along with what is
+// wrong with it.  Subtitles which match the format but use an unconventional
+// language code are also reported.  Returns whether anything was printed
+fn report_subtitle_format(sub: &str, summary: &mut SubtitleSummary) -> bool {
+    let well_formatted = check_subtitle_format(sub, &constants::SUB_RE);
+    let diagnostic = diagnostics::diagnose_subtitle(sub);
+    if well_formatted && diagnostic.is_none() {
+        return false;
+    }
+    if well_formatted {
+        summary.unconventionally_named += 1;
+    } else {
+        summary.incorrectly_formatted += 1;
+    }
+    println!(
+        "\t{}{}{}",
+        "Subtitle file ".italic(),
+        sub.bold(),
+        if well_formatted {
+            " is unconventionally named".italic()
+        } else {
+            " is incorrectly formatted".italic()
+        }
+    );
+    // The proposed name, which also matches the media file, is printed instead
+    // of the diagnostic's suggestion
+    if let Some(mut diagnostic) = diagnostic {
+        diagnostic.suggestion = None;
+        diagnostic.print(2);
+    }
+    true
 }
 
 fn check_subtitle_format(sub: &str, pattern: &Regex) -> bool {
     pattern.is_match(sub)
 }
+
+// Print a subtitle file if its base name does not match any media file in
+// the same directory, along with the media file it was most likely meant for.
+// Returns whether anything was printed
+fn report_subtitle_media(sub: &Path, summary: &mut SubtitleSummary) -> bool {
+    let name = sub.file_name().unwrap().to_str().unwrap();
+    let sub_name = match SubtitleName::parse(name) {
+        Some(sub_name) => sub_name,
+        // We cannot tell which media file a badly-formatted subtitle is for
+        None => return false,
+    };
+    let base_name = sub_name.fname.clone();
+    let media_names = sibling_media_names(sub);
+    if media_names.contains(&base_name) {
+        return false;
+    }
+    match closest_media_name(&base_name, &media_names) {
+        Some(media_name) => {
+            summary.mismatched += 1;
+            println!(
+                "\t{}{} [{}]{}{}",
+                "Subtitle file ".italic(),
+                name.bold(),
+                sub_name.describe(),
+                " does not match its media file; did you mean ".italic(),
+                media_name.bold()
+            )
+        }
+        None => {
+            summary.orphaned += 1;
+            println!(
+                "\t{}{} [{}]{}",
+                "Subtitle file ".italic(),
+                name.bold(),
+                sub_name.describe(),
+                " has no matching media file".italic()
+            )
+        }
+    }
+    true
+}
+
+// Print a VobSub file if the other half of its pair is missing: the .idx file
+// only indexes the images in the .sub file, and neither is any use alone.
+// Returns whether anything was printed
+fn report_vobsub_pair(sub: &Path, summary: &mut SubtitleSummary) -> bool {
+    let partner = match path::get_extension_from_filename(sub) {
+        Some("idx") => "sub",
+        // Otherwise, it is MicroDVD text, which needs no .idx
+        Some("sub") if path::is_vobsub_images(sub) => "idx",
+        _ => return false,
+    };
+    if sub.with_extension(partner).exists() {
+        return false;
+    }
+    summary.unpaired += 1;
+    println!(
+        "\t{}{}{}",
+        "Subtitle file ".italic(),
+        sub.file_name().unwrap().to_string_lossy().bold(),
+        format!(" has no matching .{} file", partner).italic()
+    );
+    true
+}
+
+// The names (without extension) of media files in the same directory as a file
+fn sibling_media_names(p: &Path) -> Vec<String> {
+    let parent = match p.parent() {
+        Some(parent) => parent,
+        None => return Vec::new(),
+    };
+    let mut names: Vec<_> = fs::read_dir(parent)
+        .expect("Cannot read directory")
+        .map(|e| e.expect("Cannot retreive file information").path())
+        .filter(|p| path::is_media_file(p))
+        .filter_map(|p| Some(p.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+// Find the media file a subtitle was probably meant for.  An episode with the
+// same episode number is preferred; otherwise, we look for a media name which
+// only differs slightly (e.g., a typo, or a missing episode title)
+fn closest_media_name<'a>(base_name: &str, media_names: &'a [String]) -> Option<&'a String> {
+    let ep_num = |name: &str| {
+        constants::EP_RE
+            .captures(&format!("{}.ext", name))
+            .map(|caps| (caps["snum"].to_string(), caps["epnum"].to_string()))
+    };
+    if let Some(sub_ep) = ep_num(base_name) {
+        if let Some(media_name) = media_names
+            .iter()
+            .find(|m| ep_num(m).as_ref() == Some(&sub_ep))
+        {
+            return Some(media_name);
+        }
+        // The episode we are looking for does not exist
+        return None;
+    }
+    if let Some(media_name) = media_names
+        .iter()
+        .find(|m| m.starts_with(base_name) || base_name.starts_with(m.as_str()))
+    {
+        return Some(media_name);
+    }
+    media_names
+        .iter()
+        .map(|m| (edit_distance(base_name, m), m))
+        .filter(|(d, m)| *d <= (m.chars().count() / 4).max(2))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, m)| m)
+}
+
+// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
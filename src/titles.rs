@@ -6,12 +6,17 @@ use colored::*;
 
 use super::constants;
 use super::dir::MediaType;
+use super::episodes;
+use super::manifest::Manifest;
 
 pub fn check_series_titles(dirname: &Path) {
     let mut series_dir = dirname.to_path_buf();
     series_dir.push(MediaType::Series.as_str());
     // Construct a hashmap for storing results
     let mut missing_ep_names_map = HashMap::<String, Vec<isize>>::new();
+    // Series with absolute numbering are stored by episode rather than season
+    let mut missing_abs_ep_names_map = HashMap::<String, Vec<isize>>::new();
+    let manifest = Manifest::load(dirname);
     // Get series available
     let series: Vec<_> = fs::read_dir(&series_dir)
         .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", series_dir))
@@ -20,7 +25,14 @@ pub fn check_series_titles(dirname: &Path) {
     // Search through series
     for path in series {
         let series_name_outer = &path.file_name().unwrap().to_str().unwrap().to_string();
-        if path.is_dir() {
+        if path.is_dir() && manifest.get(&path).is_some_and(|m| m.absolute) {
+            let missing: Vec<isize> = episodes::absolute_episodes(&path)
+                .iter()
+                .filter(|e| e.name.is_none())
+                .map(|e| e.num)
+                .collect();
+            missing_abs_ep_names_map.insert(series_name_outer.to_string(), missing);
+        } else if path.is_dir() {
             missing_ep_names_map.insert(series_name_outer.to_string(), vec![]);
             let contents: Vec<_> = fs::read_dir(&path)
                 .expect("Cannot read directory")
@@ -84,4 +96,12 @@ pub fn check_series_titles(dirname: &Path) {
             println!("\t{}{}", "Season ".blue(), si.to_string().blue())
         }
     }
+    for (s, v) in missing_abs_ep_names_map.iter() {
+        if !v.is_empty() {
+            println!("{}", &s.blue().bold())
+        }
+        for ei in v.iter() {
+            println!("\t{}{}", "Episode ".blue(), ei.to_string().blue())
+        }
+    }
 }
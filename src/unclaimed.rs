@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use super::constants;
+use super::dir::{self, MediaType};
+use super::manifest;
+use super::path;
+
+// List every file in the library which is not recognised by any of our naming
+// rules, grouped by extension and by directory.  Files are ignored if their
+// extension or full name is in the ignore list
+pub fn list_unclaimed_files(dirname: &Path, media_type: &MediaType, ignore: &[String]) {
+    for library in media_type.libraries() {
+        let mut library_dir = dirname.to_path_buf();
+        library_dir.push(library.as_str());
+        let files = dir::list_files(&library_dir)
+            .unwrap_or_else(|_| panic!("Cannot read directory: {:?}", library_dir));
+        let unclaimed: Vec<_> = files
+            .into_iter()
+            .filter(|p| !is_ignored(p, ignore) && !is_claimed(p, &library))
+            .collect();
+
+        println!("{}", library.as_str().blue().bold());
+        if unclaimed.is_empty() {
+            println!("\t{}", "No unclaimed files".italic());
+            continue;
+        }
+
+        // Group by extension
+        let mut by_ext = BTreeMap::<String, usize>::new();
+        for p in &unclaimed {
+            let ext = path::get_extension_from_filename(p)
+                .map(str::to_lowercase)
+                .unwrap_or_else(|| "<none>".to_string());
+            *by_ext.entry(ext).or_default() += 1;
+        }
+        println!("\t{}", "By extension:".italic());
+        for (ext, cnt) in by_ext {
+            println!("\t\t{}: {}", ext.bold(), cnt);
+        }
+
+        // Group by directory, relative to the library
+        let mut by_dir = BTreeMap::<PathBuf, Vec<String>>::new();
+        for p in &unclaimed {
+            let parent = p.parent().unwrap_or(&library_dir);
+            let rel = parent.strip_prefix(&library_dir).unwrap_or(parent);
+            by_dir
+                .entry(rel.to_path_buf())
+                .or_default()
+                .push(p.file_name().unwrap().to_string_lossy().to_string());
+        }
+        println!("\t{}", "By directory:".italic());
+        for (d, files) in by_dir {
+            let d = if d.as_os_str().is_empty() {
+                ".".to_string()
+            } else {
+                d.display().to_string()
+            };
+            println!("\t\t{}", d.bold());
+            for f in files {
+                println!("\t\t\t{}", f);
+            }
+        }
+    }
+}
+
+fn is_ignored(p: &Path, ignore: &[String]) -> bool {
+    let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let ext = path::get_extension_from_filename(p).unwrap_or("");
+    ignore
+        .iter()
+        .any(|i| i == name || i.trim_start_matches('.').eq_ignore_ascii_case(ext))
+}
+
+// Whether any of our naming rules recognises a file
+fn is_claimed(p: &Path, library: &MediaType) -> bool {
+    let name = match p.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    let ext = path::get_extension_from_filename(p).unwrap_or("");
+    if name == manifest::MANIFEST_FILE_NAME {
+        return *library == MediaType::Series;
+    }
+    if path::is_subtitle_file(p) {
+        return constants::SUB_RE.is_match(name);
+    }
+    if !constants::MEDIA_TYPES.contains(&ext) {
+        return false;
+    }
+    match library {
+        MediaType::Film => p
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(|s| constants::FILM_RE.is_match(s))
+            .unwrap_or(false),
+        MediaType::Series => constants::EP_RE.is_match(name) || constants::ABS_EP_RE.is_match(name),
+        MediaType::Unknown => false,
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use colored::*;
+
+use super::cue::{Problem, SubtitleFormat};
+use super::encoding;
+use super::subtitles;
+
+// Check the contents of subtitle files, reporting each problem found along
+// with the line it is on
+pub fn validate_subtitles(paths: &[PathBuf]) {
+    let mut checked = 0;
+    let mut with_problems = 0;
+    for sub in subtitles::find_subtitles(paths) {
+        let format = match SubtitleFormat::from_path(&sub) {
+            Some(format) => format,
+            None => continue,
+        };
+        let contents = match encoding::read_to_string(&sub) {
+            Ok((contents, _)) => contents,
+            Err(e) => {
+                eprintln!("[ERROR] Cannot read {:?}: {}", sub, e);
+                continue;
+            }
+        };
+        checked += 1;
+        let (_, mut problems): (_, Vec<Problem>) = format.parse(&contents);
+        if problems.is_empty() {
+            continue;
+        }
+        with_problems += 1;
+        problems.sort_by_key(|p| p.line);
+        println!("{} ({})", sub.display().to_string().bold(), format);
+        for problem in problems {
+            println!(
+                "\t{}{}",
+                format!("Line {}: ", problem.line).blue(),
+                problem.message
+            );
+        }
+    }
+    println!(
+        "{}{}{}{}",
+        with_problems.to_string().bold(),
+        " of ".italic(),
+        checked.to_string().bold(),
+        " subtitle files have problems".italic()
+    );
+}
@@ -0,0 +1,148 @@
+// This file provides a parser for WebVTT (.vtt) subtitle files
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::cue::{self, Cue, Problem};
+
+lazy_static! {
+    // A timestamp within cue text, as used for karaoke-style captions
+    static ref INLINE_TIMESTAMP_RE: Regex = Regex::new(r"<(?P<ts>(\d+:)?\d{2}:\d{2}\.\d{3})>").unwrap();
+}
+
+// Parse a WebVTT file into its cues, along with any problems found in it.
+// Comment, style, and region blocks are skipped
+pub fn parse(contents: &str) -> (Vec<Cue>, Vec<Problem>) {
+    let lines = cue::lines(contents);
+    let mut cues = Vec::new();
+    let mut problems = Vec::new();
+    if lines.iter().all(|l| l.trim().is_empty()) {
+        problems.push(Problem::new(1, "File is empty"));
+        return (cues, problems);
+    }
+
+    let mut blocks = cue::blocks(&lines).into_iter();
+    // The first block is the header, which must start with "WEBVTT"
+    let has_header =
+        lines[0] == "WEBVTT" || lines[0].starts_with("WEBVTT ") || lines[0].starts_with("WEBVTT\t");
+    if has_header {
+        blocks.next();
+    } else {
+        problems.push(Problem::new(1, "Missing \"WEBVTT\" header"));
+    }
+
+    for (start, block) in blocks {
+        if is_metadata_block(block[0]) {
+            continue;
+        }
+        let mut block = block.iter().enumerate().map(|(i, l)| (start + i, *l));
+        let (mut line_num, mut line) = block.next().unwrap();
+
+        // Optional cue identifier
+        if !line.contains("-->") {
+            match block.next() {
+                Some((n, l)) if l.contains("-->") => {
+                    line_num = n;
+                    line = l;
+                }
+                _ => {
+                    problems.push(Problem::new(
+                        line_num,
+                        format!("Expected cue timestamps after \"{}\"", line.trim()),
+                    ));
+                    continue;
+                }
+            }
+        }
+
+        // Timestamps
+        let timing = cue::split_timing_line(line).and_then(|(start, end, settings)| {
+            Some((
+                start,
+                end,
+                settings,
+                cue::parse_timestamp(start, true)?,
+                cue::parse_timestamp(end, true)?,
+            ))
+        });
+        let (start_ms, end_ms, settings) = match timing {
+            Some((start, end, settings, start_ms, end_ms)) => {
+                if start.contains(',') || end.contains(',') {
+                    problems.push(Problem::new(
+                        line_num,
+                        "Timestamps should use '.' rather than ',' before the milliseconds",
+                    ));
+                }
+                (start_ms, end_ms, settings)
+            }
+            None => {
+                problems.push(Problem::new(
+                    line_num,
+                    format!("Malformed timestamps \"{}\"", line.trim()),
+                ));
+                continue;
+            }
+        };
+
+        // Text
+        let text: Vec<String> = block.map(|(_, l)| l.to_string()).collect();
+        if text.is_empty() {
+            problems.push(Problem::new(line_num, "Cue has no text"));
+        }
+        cues.push(Cue {
+            start: start_ms,
+            end: end_ms,
+            line: line_num,
+            text,
+            settings: settings.to_string(),
+            style: String::new(),
+        });
+    }
+    problems.extend(cue::check_timing(&cues));
+    (cues, problems)
+}
+
+// Blocks which are not cues: comments, style sheets, and region definitions
+fn is_metadata_block(first_line: &str) -> bool {
+    ["NOTE", "STYLE", "REGION"].iter().any(|kw| {
+        first_line == *kw
+            || first_line.starts_with(&format!("{} ", kw))
+            || first_line.starts_with(&format!("{}\t", kw))
+    })
+}
+
+// Write cues as a WebVTT file, keeping any cue settings.  Cue text should
+// already use WebVTT markup
+pub fn write(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for c in cues {
+        out.push_str(&format!(
+            "{} --> {}",
+            cue::format_timestamp(c.start, '.'),
+            cue::format_timestamp(c.end, '.')
+        ));
+        if !c.settings.is_empty() {
+            out.push(' ');
+            out.push_str(&c.settings);
+        }
+        out.push_str(&format!("\n{}\n\n", c.text.join("\n")));
+    }
+    out
+}
+
+// Rewrite the times of every cue with `f`, including timestamps within cue
+// text
+pub fn retime(contents: &str, f: &dyn Fn(i64) -> i64) -> String {
+    cue::map_lines(contents, |line| {
+        if let Some(line) = cue::retime_timing_line(line, true, '.', f) {
+            return line;
+        }
+        INLINE_TIMESTAMP_RE
+            .replace_all(line, |caps: &regex::Captures| {
+                match cue::parse_timestamp(&caps["ts"], true) {
+                    Some(ms) => format!("<{}>", cue::format_timestamp(f(ms), '.')),
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string()
+    })
+}